@@ -0,0 +1,121 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use plaine::plan::{
+    condition::Condition,
+    ledger::{self, Ledger, Transaction, TransactionKind},
+    Entry, Plan,
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts every allocation, so a frame can be shown to make none.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ENTRIES: usize = 50_000;
+
+/// Four Fnskus to a case, each Fnsku spread over many cases.
+fn entries() -> Vec<Entry> {
+    (0..ENTRIES)
+        .map(|idx| {
+            let mut entry = Entry::default();
+            entry.set_id(format!("Box-{}", idx / 4));
+            entry.set_fnsku(format!("X00{:06}", idx % 5_000));
+            entry.set_units(idx as i32 % 24 + 1);
+            entry.set_condition(Some(Condition::ALL[idx % Condition::ALL.len()]));
+            entry.set_total_pounds(Some(idx as f32 % 60.0));
+            entry
+        })
+        .collect()
+}
+
+/// A ledger of `entries`, half of which have since moved out.
+fn ledger(entries: Vec<Entry>) -> Ledger {
+    let dir = std::env::temp_dir().join(format!("plaine-bench-{}", std::process::id()));
+    let moved = entries
+        .iter()
+        .step_by(2)
+        .map(|x| {
+            let mut moved = x.clone();
+            moved.set_units(-x.get_units());
+            moved
+        })
+        .collect();
+    let import = Transaction::new("bench", TransactionKind::Import, entries);
+    let move_out = Transaction::new("bench", TransactionKind::MoveOut, moved);
+    ledger::write_all(&dir, &[&import, &move_out]).expect("Write Bench Ledger");
+    let ledger = Ledger::load(&dir, "bench").expect("Load Bench Ledger");
+    std::fs::remove_dir_all(&dir).expect("Remove Bench Ledger");
+    ledger
+}
+
+/// Read a summary the way the branch contents and case views do.
+fn frame(ledger: &Ledger) -> i32 {
+    let summary = ledger.summary();
+    let mut units = summary
+        .sums_of(Some(Condition::New))
+        .iter()
+        .filter(|x| x.get_units() > 0)
+        .map(|x| x.get_units())
+        .sum::<i32>();
+    units += summary.conditions().len() as i32;
+    for (id, case) in summary.cases() {
+        units += id.len() as i32 + case.units();
+    }
+    units
+}
+
+fn aggregates(c: &mut Criterion) {
+    let plan = entries();
+    let mut group = c.benchmark_group("50k entries");
+    group.bench_function("get_as_sums", |b| b.iter(|| black_box(&plan).get_as_sums()));
+    group.bench_function("units_of_skus", |b| {
+        b.iter(|| black_box(&plan).units_of_skus().len())
+    });
+    group.bench_function("as_group_by_case", |b| {
+        b.iter(|| black_box(&plan).as_group_by_case().len())
+    });
+    group.bench_function("remaining", |b| b.iter(|| black_box(&plan).remaining()));
+    group.bench_function("summarize", |b| b.iter(|| black_box(&plan).summarize()));
+    group.finish();
+}
+
+fn frames(c: &mut Criterion) {
+    let ledger = ledger(entries());
+    let unsummarized = ledger.clone();
+    frame(&ledger);
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(frame(&ledger));
+    let allocated = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(allocated, 0, "A frame of a summarized ledger allocated");
+
+    let mut group = c.benchmark_group("50k entry ledger");
+    group.bench_function("first frame", |b| {
+        b.iter_batched(
+            || unsummarized.clone(),
+            |ledger| frame(&ledger),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("frame", |b| b.iter(|| frame(black_box(&ledger))));
+    group.finish();
+}
+
+criterion_group!(benches, aggregates, frames);
+criterion_main!(benches);
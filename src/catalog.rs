@@ -0,0 +1,993 @@
+use crate::{
+    plan::{condition::Condition, hazmat::Hazmat, marketplace::Marketplace, Entry},
+    read::{may_hold_report, read_products, Diagnostics, ReportKind, ReportMonth},
+};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Location of the on disk cache, relative to the directory being cataloged.
+const CACHE_FILE: &str = "CACHE/catalog.json";
+
+/// Bumped whenever [`Product`] or [`ReportKind`] learn something new, which
+/// discards older caches.
+const CACHE_VERSION: u32 = 9;
+
+/// Where a value used for enrichment was read from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Source {
+    pub kind: ReportKind,
+    pub file: String,
+    pub line: u64,
+}
+
+impl Source {
+    pub fn new<P>(kind: ReportKind, file: P, line: u64) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let file = file.as_ref().display().to_string();
+        Self { kind, file, line }
+    }
+
+    /// Tag an optional value as having been read from [`Self`].
+    pub fn tag<T>(&self, value: Option<T>) -> Option<Sourced<T>> {
+        value.map(|value| Sourced {
+            value,
+            source: self.clone(),
+        })
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} {}:{}", self.kind, self.file, self.line)
+    }
+}
+
+/// A value along with the [`Source`] it was read from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sourced<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+/// The fields of a [`Product`] that are read from reports.
+///
+/// Every field but [`Field::Available`] and [`Field::UnitVolume`] is filled
+/// into an [`Entry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Field {
+    Msku,
+    Asin,
+    Title,
+    Upc,
+    AmzSize,
+    Condition,
+    TotalPounds,
+    AmzDimensions,
+    Available,
+    UnitVolume,
+    Hazmat,
+}
+
+/// Two reports disagree on the value of a [`Field`] for the same product.
+///
+/// `kept` is the value that was used, `replaced` is the value it overrode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub key: String,
+    pub field: Field,
+    pub kept: Sourced<String>,
+    pub replaced: Sourced<String>,
+}
+
+/// A value that can be compared between reports, and shown when they differ.
+trait Describe: PartialEq {
+    fn describe(&self) -> String;
+}
+
+impl Describe for String {
+    fn describe(&self) -> String {
+        self.clone()
+    }
+}
+
+impl Describe for Condition {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for Hazmat {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for u32 {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for f32 {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Describe for [f32; 3] {
+    fn describe(&self) -> String {
+        let [l, w, h] = self;
+        format!("{l} x {w} x {h}")
+    }
+}
+
+/// Everything that is known about a single product, across all reports.
+///
+/// A [`Product`] is keyed by its Fnsku, Msku, and Asin. Reports only ever
+/// describe part of a product, so each row of a report is turned into a
+/// partial [`Product`] before being merged into the [`Catalog`].
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Product {
+    pub(crate) fnsku: Option<String>,
+    pub(crate) msku: Option<Sourced<String>>,
+    pub(crate) asin: Option<Sourced<String>>,
+    pub(crate) title: Option<Sourced<String>>,
+    pub(crate) upc: Option<Sourced<String>>,
+    pub(crate) amz_size: Option<Sourced<String>>,
+    pub(crate) condition: Option<Sourced<Condition>>,
+    pub(crate) pounds: Option<Sourced<f32>>,
+    pub(crate) amz_dimensions: Option<Sourced<[f32; 3]>>,
+    /// Sellable units within Amazon's warehouses.
+    pub(crate) available: Option<Sourced<u32>>,
+    /// Volume of a single unit, in cubic feet.
+    pub(crate) volume: Option<Sourced<f32>>,
+    pub(crate) hazmat: Option<Sourced<Hazmat>>,
+    /// The marketplace that the report row describes, `None` for any.
+    pub(crate) marketplace: Option<Marketplace>,
+    /// The month that the report row describes, `None` when undated.
+    pub(crate) dated: Option<ReportMonth>,
+    /// Where Amazon stored the product, in its latest storage fee report.
+    #[serde(default)]
+    pub(crate) fulfillment_centers: BTreeSet<String>,
+    /// Amazon's storage rate per cubic foot, in the month that is `dated`.
+    #[serde(default)]
+    pub(crate) storage_rate: Option<f32>,
+    /// Amazon's storage fee of a single unit, in the month that is `dated`.
+    #[serde(default)]
+    pub(crate) unit_storage_fee: Option<f32>,
+}
+
+impl Product {
+    pub fn get_fnsku(&self) -> Option<&str> {
+        self.fnsku.as_deref()
+    }
+
+    pub fn get_msku(&self) -> Option<&str> {
+        self.msku.as_ref().map(|x| x.value.as_str())
+    }
+
+    pub fn get_asin(&self) -> Option<&str> {
+        self.asin.as_ref().map(|x| x.value.as_str())
+    }
+
+    pub fn get_title(&self) -> Option<&str> {
+        self.title.as_ref().map(|x| x.value.as_str())
+    }
+
+    pub fn get_amz_size(&self) -> Option<&str> {
+        self.amz_size.as_ref().map(|x| x.value.as_str())
+    }
+
+    pub fn get_volume(&self) -> Option<f32> {
+        self.volume.as_ref().map(|x| x.value)
+    }
+
+    pub fn get_available(&self) -> Option<u32> {
+        self.available.as_ref().map(|x| x.value)
+    }
+
+    pub fn get_dated(&self) -> Option<ReportMonth> {
+        self.dated
+    }
+
+    pub fn get_fulfillment_centers(&self) -> &BTreeSet<String> {
+        &self.fulfillment_centers
+    }
+
+    /// Returns the rate that Amazon charged, and the month it was charged.
+    pub fn get_storage_rate(&self) -> Option<(ReportMonth, f32)> {
+        Some((self.dated?, self.storage_rate?))
+    }
+
+    pub fn get_unit_storage_fee(&self) -> Option<f32> {
+        self.unit_storage_fee
+    }
+
+    /// Returns the dimensions and weight that [`Self`] was measured at.
+    ///
+    /// Dimensions of all zeros are read from empty columns, and are ignored.
+    fn measurement(&self) -> Option<Measurement> {
+        let dims = self.amz_dimensions.as_ref().filter(|x| x.value != [0.0; 3]);
+        let pounds = self.pounds.as_ref();
+        let source = dims
+            .map(|x| x.source.clone())
+            .or_else(|| pounds.map(|x| x.source.clone()))?;
+        Some(Measurement {
+            dated: self.dated,
+            dimensions: dims.map(|x| x.value),
+            pounds: pounds.map(|x| x.value),
+            source,
+        })
+    }
+
+    /// The key that conflicts for [`Self`] are reported under.
+    fn key(&self) -> String {
+        let msku = || self.get_msku();
+        self.get_fnsku()
+            .or_else(msku)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Merge `other` into [`Self`], values present in `other` take priority.
+    ///
+    /// Returns a [`Conflict`] for each field where the values differed,
+    /// except between reports of the same kind describing different months,
+    /// which are remeasurements instead.
+    fn absorb(&mut self, other: Product) -> Vec<Conflict> {
+        let key = &self.key();
+        let remeasured = self.dated.is_some() && other.dated.is_some() && self.dated != other.dated;
+        if other.fnsku.is_some() {
+            self.fnsku = other.fnsku;
+        };
+        if other.marketplace.is_some() {
+            self.marketplace = other.marketplace;
+        };
+        // A later month replaces the fulfillment centers of earlier ones.
+        if !other.fulfillment_centers.is_empty() && other.dated != self.dated {
+            self.fulfillment_centers.clear();
+        };
+        self.fulfillment_centers.extend(other.fulfillment_centers);
+        if other.storage_rate.is_some() {
+            self.storage_rate = other.storage_rate;
+        };
+        if other.unit_storage_fee.is_some() {
+            self.unit_storage_fee = other.unit_storage_fee;
+        };
+        if other.dated.is_some() {
+            self.dated = other.dated;
+        };
+        let dims = &mut self.amz_dimensions;
+        let conflicts = [
+            take(key, Field::Msku, &mut self.msku, other.msku),
+            take(key, Field::Asin, &mut self.asin, other.asin),
+            take(key, Field::Title, &mut self.title, other.title),
+            take(key, Field::Upc, &mut self.upc, other.upc),
+            take(key, Field::AmzSize, &mut self.amz_size, other.amz_size),
+            take(key, Field::Condition, &mut self.condition, other.condition),
+            take(key, Field::TotalPounds, &mut self.pounds, other.pounds),
+            take(key, Field::AmzDimensions, dims, other.amz_dimensions),
+            take(key, Field::Available, &mut self.available, other.available),
+            take(key, Field::UnitVolume, &mut self.volume, other.volume),
+            take(key, Field::Hazmat, &mut self.hazmat, other.hazmat),
+        ];
+        conflicts
+            .into_iter()
+            .flatten()
+            .filter(|x| !remeasured || x.kept.source.kind != x.replaced.source.kind)
+            .collect()
+    }
+
+    /// Copy the item information of [`Self`] into `entry`.
+    ///
+    /// Fields that [`Self`] knows nothing about are left untouched, every
+    /// field that is set also records its [`Source`] within `entry`.
+    fn fill(&self, entry: &mut Entry) {
+        if let Some(msku) = &self.msku {
+            entry.set_msku(Some(msku.value.clone()));
+            entry.set_source(Field::Msku, Some(msku.source.clone()));
+        };
+        if let Some(asin) = &self.asin {
+            entry.set_asin(Some(asin.value.clone()));
+            entry.set_source(Field::Asin, Some(asin.source.clone()));
+        };
+        if let Some(title) = &self.title {
+            entry.set_title(Some(title.value.clone()));
+            entry.set_source(Field::Title, Some(title.source.clone()));
+        };
+        if let Some(upc) = &self.upc {
+            entry.set_upc(Some(upc.value.clone()));
+            entry.set_source(Field::Upc, Some(upc.source.clone()));
+        };
+        if let Some(amz_size) = &self.amz_size {
+            entry.set_amz_size(Some(amz_size.value.clone()));
+            entry.set_source(Field::AmzSize, Some(amz_size.source.clone()));
+        };
+        if let Some(condition) = &self.condition {
+            entry.set_condition(Some(condition.value));
+            entry.set_source(Field::Condition, Some(condition.source.clone()));
+        };
+        if let Some(pounds) = &self.pounds {
+            entry.set_total_pounds(Some(pounds.value));
+            entry.set_source(Field::TotalPounds, Some(pounds.source.clone()));
+        };
+        if let Some(dims) = &self.amz_dimensions {
+            // entry type has a separate field for input dims.
+            // doing this does not overwrite them.
+            entry.set_amz_dimensions(Some(dims.value));
+            entry.set_source(Field::AmzDimensions, Some(dims.source.clone()));
+        };
+        if let Some(hazmat) = &self.hazmat {
+            entry.set_hazmat(Some(hazmat.value));
+            entry.set_source(Field::Hazmat, Some(hazmat.source.clone()));
+        };
+    }
+}
+
+/// Replace `current` with `other` when it is present.
+///
+/// Returns a [`Conflict`] when both are present and their values differ.
+fn take<T: Describe>(
+    key: &str,
+    field: Field,
+    current: &mut Option<Sourced<T>>,
+    other: Option<Sourced<T>>,
+) -> Option<Conflict> {
+    let other = other?;
+    let conflict = match current.take() {
+        Some(old) if old.value != other.value => Some(Conflict {
+            key: key.to_string(),
+            field,
+            kept: Sourced {
+                value: other.value.describe(),
+                source: other.source.clone(),
+            },
+            replaced: Sourced {
+                value: old.value.describe(),
+                source: old.source,
+            },
+        }),
+        _ => None,
+    };
+    *current = Some(other);
+    conflict
+}
+
+/// The dimensions and weight of a product, as read from a single report row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub dated: Option<ReportMonth>,
+    pub dimensions: Option<[f32; 3]>,
+    pub pounds: Option<f32>,
+    pub source: Source,
+}
+
+impl Measurement {
+    /// Returns true if [`Self`] and `other` differ by more than rounding.
+    pub fn differs(&self, other: &Measurement) -> bool {
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+        let dims = match (self.dimensions, other.dimensions) {
+            (Some(a), Some(b)) => a.into_iter().zip(b).all(|(a, b)| close(a, b)),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        let pounds = match (self.pounds, other.pounds) {
+            (Some(a), Some(b)) => close(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        !(dims && pounds)
+    }
+}
+
+/// The outcome of enriching a batch of [`Entry`]s.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EnrichmentReport {
+    /// Fnskus that could not be found within any report.
+    pub unmatched: Vec<String>,
+    /// Disagreements between reports, for the products that were used.
+    pub conflicts: Vec<Conflict>,
+    /// Fnskus that Amazon measured differently over time, see
+    /// [`Catalog::measurements`].
+    #[serde(default)]
+    pub remeasured: Vec<String>,
+}
+
+impl EnrichmentReport {
+    pub fn is_empty(&self) -> bool {
+        self.unmatched.is_empty() && self.conflicts.is_empty() && self.remeasured.is_empty()
+    }
+}
+
+/// An indexed collection of [`Product`]s built from imported reports.
+///
+/// Lookups by Fnsku, Msku, or Asin are all hash lookups. Note that an Asin
+/// may be shared between several products, e.g. a new and used condition.
+#[derive(Debug, Default, Clone)]
+pub struct Catalog {
+    products: Vec<Product>,
+    conflicts: Vec<Vec<Conflict>>,
+    by_fnsku: HashMap<String, usize>,
+    by_msku: HashMap<String, usize>,
+    by_asin: HashMap<String, Vec<usize>>,
+    /// Each change in measurement, keyed by Fnsku.
+    history: HashMap<String, Vec<Measurement>>,
+    diagnostics: Diagnostics,
+}
+
+impl Catalog {
+    /// Build a [`Catalog`] from every report within `dir`.
+    ///
+    /// Parsed reports are cached within `dir`, keyed by their path. A report
+    /// is only read again when its size, modification time, and content hash
+    /// no longer match the cache, and the cache is only written when a report
+    /// was read, stamped again, or removed. Files that cannot hold a report,
+    /// such as ledger transactions, are skipped.
+    ///
+    /// A report that cannot be read, or a cache that cannot be written, is
+    /// recorded within [`Self::diagnostics`] instead.
+    ///
+    /// # Errors
+    ///
+    /// `dir` cannot be read.
+    pub fn load<P>(dir: P) -> Result<Catalog>
+    where
+        P: AsRef<Path>,
+    {
+        Catalog::load_for(dir, None)
+    }
+
+    /// Build a [`Catalog`] for `marketplace` from every report within `dir`.
+    ///
+    /// Report rows of other marketplaces are left out, rows that do not name
+    /// a marketplace are kept. A `marketplace` of `None` keeps every row.
+    ///
+    /// See [`Self::load`].
+    pub fn load_for<P>(dir: P, marketplace: Option<Marketplace>) -> Result<Catalog>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        let cache_path = dir.join(CACHE_FILE);
+        let mut cache = Cache::read(&cache_path).unwrap_or_default();
+        let mut fresh = Cache::default();
+
+        let files = std::fs::read_dir(dir)?
+            .filter_map(|x| x.ok())
+            .filter(|x| x.file_type().map(|t| t.is_file()).unwrap_or_default())
+            .filter(|x| may_hold_report(x.path()));
+
+        let mut unread = Diagnostics::default();
+        let mut changed = false;
+        for file in files {
+            let path = file.path();
+            let cached = cache.sources.remove(&path);
+            let stamp = cached.as_ref().map(|x| x.stamp);
+            match CachedSource::refresh(&path, cached) {
+                Ok(source) => {
+                    changed |= stamp != Some(source.stamp);
+                    fresh.sources.insert(path, source);
+                }
+                Err(err) => {
+                    changed |= stamp.is_some();
+                    unread.reject(&path, None, &err);
+                }
+            };
+        }
+        // Any source left in the cache is of a file that is gone.
+        changed |= !cache.sources.is_empty();
+        if changed {
+            if let Err(err) = fresh.write(&cache_path) {
+                unread.reject(&cache_path, None, &err);
+            };
+        };
+        let mut catalog = fresh.into_catalog(marketplace);
+        catalog.diagnostics.extend(unread);
+        Ok(catalog)
+    }
+
+    /// Build a [`Catalog`] by merging each partial [`Product`] in order.
+    ///
+    /// Later products override the values of earlier ones.
+    pub fn from_products<I>(products: I) -> Catalog
+    where
+        I: IntoIterator<Item = Product>,
+    {
+        let mut catalog = Catalog::default();
+        products.into_iter().for_each(|p| catalog.insert(p));
+        catalog
+    }
+
+    /// Merge `product` into an existing record by Fnsku or Msku, or add it.
+    fn insert(&mut self, product: Product) {
+        let measurement = product.measurement();
+        let fnsku_idx = product.get_fnsku().and_then(|k| self.by_fnsku.get(k));
+        let msku_idx = product.get_msku().and_then(|k| self.by_msku.get(k));
+
+        let idx = match fnsku_idx.or(msku_idx).copied() {
+            Some(idx) => {
+                if let Some(old_asin) = self.products[idx].get_asin() {
+                    if product.get_asin().is_some_and(|new| new != old_asin) {
+                        if let Some(idxs) = self.by_asin.get_mut(old_asin) {
+                            idxs.retain(|x| x != &idx);
+                        };
+                    };
+                };
+                let conflicts = self.products[idx].absorb(product);
+                self.conflicts[idx].extend(conflicts);
+                idx
+            }
+            None => {
+                self.products.push(product);
+                self.conflicts.push(vec![]);
+                self.products.len() - 1
+            }
+        };
+        self.index(idx);
+        if let (Some(measurement), Some(fnsku)) = (measurement, self.products[idx].get_fnsku()) {
+            let history = self.history.entry(fnsku.to_string()).or_default();
+            if history.last().is_none_or(|x| x.differs(&measurement)) {
+                history.push(measurement);
+            };
+        };
+    }
+
+    /// Add the keys of the product at `idx` to each index.
+    fn index(&mut self, idx: usize) {
+        let product = &self.products[idx];
+        if let Some(fnsku) = product.get_fnsku() {
+            self.by_fnsku.insert(fnsku.to_string(), idx);
+        };
+        if let Some(msku) = product.get_msku() {
+            self.by_msku.insert(msku.to_string(), idx);
+        };
+        if let Some(asin) = product.get_asin() {
+            let idxs = self.by_asin.entry(asin.to_string()).or_default();
+            if !idxs.contains(&idx) {
+                idxs.push(idx);
+            };
+        };
+    }
+
+    pub fn get_by_fnsku(&self, fnsku: &str) -> Option<&Product> {
+        self.by_fnsku.get(fnsku).map(|idx| &self.products[*idx])
+    }
+
+    pub fn get_by_msku(&self, msku: &str) -> Option<&Product> {
+        self.by_msku.get(msku).map(|idx| &self.products[*idx])
+    }
+
+    pub fn get_by_asin(&self, asin: &str) -> Vec<&Product> {
+        self.by_asin
+            .get(asin)
+            .map(|idxs| idxs.iter().map(|idx| &self.products[*idx]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of products within [`Self`].
+    pub fn len(&self) -> usize {
+        self.products.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.products.is_empty()
+    }
+
+    /// Returns the rows of every report that could not be read.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Returns each change in the measurements of `fnsku`, oldest first.
+    ///
+    /// Rows that repeat the previous measurement are left out, so more than
+    /// one [`Measurement`] means that Amazon remeasured the product.
+    pub fn measurements(&self, fnsku: &str) -> &[Measurement] {
+        self.history
+            .get(fnsku)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the Fnskus that have been remeasured, see [`Self::measurements`].
+    pub fn remeasured(&self) -> Vec<&str> {
+        let mut fnskus = self
+            .history
+            .iter()
+            .filter(|(_, history)| history.len() > 1)
+            .map(|(fnsku, _)| fnsku.as_str())
+            .collect::<Vec<_>>();
+        fnskus.sort();
+        fnskus
+    }
+
+    /// Returns every [`Conflict`] found while merging reports.
+    pub fn conflicts(&self) -> impl Iterator<Item = &Conflict> {
+        self.conflicts.iter().flatten()
+    }
+
+    /// Returns the index of the product that matches `entry`.
+    fn find(&self, entry: &Entry) -> Option<usize> {
+        let by_fnsku = self.by_fnsku.get(entry.get_fnsku());
+        let by_msku = || {
+            let msku = entry.get_msku().as_ref()?;
+            self.by_msku.get(msku)
+        };
+        by_fnsku.or_else(by_msku).copied()
+    }
+
+    /// Add item information to `entry`, matching first by Fnsku then Msku.
+    ///
+    /// Returns false when no product matches.
+    pub fn enrich(&self, entry: &mut Entry) -> bool {
+        let Some(idx) = self.find(entry) else {
+            return false;
+        };
+        self.products[idx].fill(entry);
+        true
+    }
+
+    /// Add item information to each of the `entries`, see [`Self::enrich`].
+    ///
+    /// The returned [`EnrichmentReport`] only lists the conflicts of products
+    /// that matched one of the `entries`.
+    pub fn enrich_all(&self, entries: &mut [Entry]) -> EnrichmentReport {
+        let mut report = EnrichmentReport::default();
+        let mut seen = HashSet::new();
+        for entry in entries {
+            match self.find(entry) {
+                Some(idx) => {
+                    self.products[idx].fill(entry);
+                    if seen.insert(idx) {
+                        report.conflicts.extend_from_slice(&self.conflicts[idx]);
+                        let fnsku = self.products[idx].get_fnsku().unwrap_or_default();
+                        if self.measurements(fnsku).len() > 1 {
+                            report.remeasured.push(fnsku.to_string());
+                        };
+                    };
+                }
+                None => {
+                    let fnsku = entry.get_fnsku().to_string();
+                    if !report.unmatched.contains(&fnsku) {
+                        report.unmatched.push(fnsku);
+                    };
+                }
+            };
+        }
+        report
+    }
+}
+
+/// Identifies the version of a file that was cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    len: u64,
+    modified: (u64, u32),
+    hash: u64,
+}
+
+impl Stamp {
+    /// Returns the length and modification time of the file at `path`.
+    fn metadata(path: &Path) -> Result<(u64, (u64, u32))> {
+        let meta = std::fs::metadata(path)?;
+        let modified = meta.modified()?.duration_since(UNIX_EPOCH)?;
+        Ok((meta.len(), (modified.as_secs(), modified.subsec_nanos())))
+    }
+
+    /// Returns the Fnv-1a hash of the file at `path`.
+    fn hash(path: &Path) -> Result<u64> {
+        let bytes = std::fs::read(path)?;
+        let hash = bytes.iter().fold(0xcbf29ce484222325_u64, |acc, b| {
+            (acc ^ *b as u64).wrapping_mul(0x100000001b3)
+        });
+        Ok(hash)
+    }
+}
+
+/// The [`Product`]s read from a single report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedReport {
+    kind: ReportKind,
+    products: Vec<Product>,
+}
+
+/// The reports read from a single file.
+///
+/// Compressed files may hold more than one report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedSource {
+    stamp: Stamp,
+    reports: Vec<CachedReport>,
+    rejected: Diagnostics,
+}
+
+impl CachedSource {
+    /// Reuse `cached` when it still matches the file at `path`, or read it.
+    fn refresh(path: &Path, cached: Option<CachedSource>) -> Result<CachedSource> {
+        let (len, modified) = Stamp::metadata(path)?;
+        if let Some(cached) = &cached {
+            if cached.stamp.len == len && cached.stamp.modified == modified {
+                return Ok(cached.to_owned());
+            };
+        };
+
+        let hash = Stamp::hash(path)?;
+        let stamp = Stamp {
+            len,
+            modified,
+            hash,
+        };
+        if let Some(mut cached) = cached {
+            if cached.stamp.hash == hash {
+                cached.stamp = stamp;
+                return Ok(cached);
+            };
+        };
+
+        let mut rejected = Diagnostics::default();
+        let reports = read_products(path, &mut rejected)?
+            .into_iter()
+            .map(|(kind, products)| CachedReport { kind, products })
+            .collect();
+        Ok(CachedSource {
+            stamp,
+            reports,
+            rejected,
+        })
+    }
+}
+
+/// The on disk cache of every report that has been read.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cache {
+    #[serde(default)]
+    version: u32,
+    sources: HashMap<PathBuf, CachedSource>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            sources: HashMap::default(),
+        }
+    }
+}
+
+impl Cache {
+    /// # Errors
+    ///
+    /// The cache cannot be read, or was written for another [`CACHE_VERSION`].
+    fn read(path: &Path) -> Result<Cache> {
+        let json = std::fs::read_to_string(path)?;
+        let cache: Cache = serde_json::from_str(&json)?;
+        if cache.version != CACHE_VERSION {
+            bail!("Cache version {} is outdated", cache.version);
+        };
+        Ok(cache)
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        };
+        let json = serde_json::to_string(&self)?;
+        Ok(std::fs::write(path, json)?)
+    }
+
+    /// Merge every cached source into a [`Catalog`].
+    ///
+    /// Storage fees are merged first, then inventory, then listings, so
+    /// that Mskus are linked to Fnskus before listings are keyed on them.
+    /// Restock reports are merged last.
+    ///
+    /// Within each kind, rows are merged from the oldest month to the most
+    /// recent, so that the latest row of a product wins. Undated rows are
+    /// merged first.
+    ///
+    /// Products of a marketplace other than `marketplace` are skipped.
+    fn into_catalog(self, marketplace: Option<Marketplace>) -> Catalog {
+        let rank = |kind: &ReportKind| match kind {
+            ReportKind::MonthlyStorageFees => 0,
+            ReportKind::AmzFbaInventory => 1,
+            ReportKind::AllListingsReport => 2,
+            ReportKind::RestockInventory => 3,
+            ReportKind::Unknown => 4,
+        };
+        let mut sources = self.sources.into_iter().collect::<Vec<_>>();
+        sources.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut diagnostics = Diagnostics::default();
+        let mut products = vec![];
+        for (_, source) in sources {
+            diagnostics.extend(source.rejected);
+            for report in source.reports {
+                let rank = rank(&report.kind);
+                let kept = report.products.into_iter().filter(|x| {
+                    marketplace.is_none()
+                        || x.marketplace.is_none()
+                        || x.marketplace == marketplace
+                });
+                products.extend(kept.map(|x| (rank, x)));
+            }
+        }
+        products.sort_by_key(|(rank, x)| (*rank, x.dated));
+        let mut catalog = Catalog::from_products(products.into_iter().map(|(_, x)| x));
+        catalog.diagnostics = diagnostics;
+        catalog
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(line: u64) -> Source {
+        Source::new(ReportKind::Unknown, "test.csv", line)
+    }
+
+    fn product(fnsku: Option<&str>, msku: Option<&str>, asin: Option<&str>) -> Product {
+        let source = source(0);
+        Product {
+            fnsku: fnsku.map(String::from),
+            msku: source.tag(msku.map(String::from)),
+            asin: source.tag(asin.map(String::from)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn catalog_merges_by_key() {
+        let mut storage = product(Some("X00A"), None, Some("B00A"));
+        storage.title = source(2).tag(Some(String::from("Storage Title")));
+        let inventory = product(Some("X00A"), Some("sku-a"), None);
+        let mut listing = product(None, Some("sku-a"), Some("B00A"));
+        listing.title = source(5).tag(Some(String::from("Listing Title")));
+        let used = product(Some("X00B"), Some("sku-b"), Some("B00A"));
+
+        let catalog = Catalog::from_products([storage, inventory, listing, used]);
+        assert_eq!(catalog.len(), 2);
+
+        let by_msku = catalog.get_by_msku("sku-a").expect("sku-a is indexed");
+        assert_eq!(by_msku.get_fnsku(), Some("X00A"));
+        assert_eq!(by_msku.get_title(), Some("Listing Title"));
+        assert_eq!(catalog.get_by_asin("B00A").len(), 2);
+
+        let mut entry = Entry::default();
+        entry.set_fnsku(String::from("X00A"));
+        assert!(catalog.enrich(&mut entry));
+        assert_eq!(entry.get_msku().as_deref(), Some("sku-a"));
+    }
+
+    #[test]
+    fn enrichment_reports_conflicts() {
+        let mut storage = product(Some("X00A"), Some("sku-a"), None);
+        storage.title = source(2).tag(Some(String::from("Storage Title")));
+        let mut listing = product(None, Some("sku-a"), None);
+        listing.title = source(5).tag(Some(String::from("Listing Title")));
+        let catalog = Catalog::from_products([storage, listing]);
+
+        let mut known = Entry::default();
+        known.set_fnsku(String::from("X00A"));
+        let mut unknown = Entry::default();
+        unknown.set_fnsku(String::from("X00Z"));
+        let mut entries = vec![known.clone(), known, unknown];
+
+        let report = catalog.enrich_all(&mut entries);
+        assert_eq!(report.unmatched, vec![String::from("X00Z")]);
+        assert_eq!(report.conflicts.len(), 1);
+
+        let conflict = &report.conflicts[0];
+        assert_eq!(conflict.field, Field::Title);
+        assert_eq!(conflict.kept.value, "Listing Title");
+        assert_eq!(conflict.replaced.source.line, 2);
+        assert_eq!(entries[0].get_source(Field::Title), Some(&source(5)));
+    }
+
+    #[test]
+    fn latest_month_wins() -> Result<()> {
+        let storage = |month: &str, pounds: f32| -> Result<CachedSource> {
+            let mut product = product(Some("X00A"), None, None);
+            product.pounds = source(2).tag(Some(pounds));
+            product.dated = Some(month.parse()?);
+            Ok(CachedSource {
+                stamp: Stamp {
+                    len: 0,
+                    modified: (0, 0),
+                    hash: 0,
+                },
+                reports: vec![CachedReport {
+                    kind: ReportKind::MonthlyStorageFees,
+                    products: vec![product],
+                }],
+                rejected: Diagnostics::default(),
+            })
+        };
+        let mut cache = Cache::default();
+        // Paths sort opposite to the months they describe.
+        let sources = [
+            ("a.csv", storage("2023-03", 2.0)?),
+            ("b.csv", storage("2023-02", 1.5)?),
+            ("c.csv", storage("2023-01", 1.5)?),
+        ];
+        cache
+            .sources
+            .extend(sources.map(|(path, x)| (PathBuf::from(path), x)));
+        let catalog = cache.into_catalog(None);
+
+        let product = catalog.get_by_fnsku("X00A").expect("X00A is indexed");
+        assert_eq!(product.get_dated(), Some("2023-03".parse()?));
+        let history = catalog.measurements("X00A");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].dated, Some("2023-01".parse()?));
+        assert_eq!(history[1].pounds, Some(2.0));
+        assert_eq!(catalog.remeasured(), vec!["X00A"]);
+        assert_eq!(catalog.conflicts().count(), 0);
+
+        let mut entry = Entry::default();
+        entry.set_fnsku(String::from("X00A"));
+        let report = catalog.enrich_all(&mut [entry]);
+        assert_eq!(report.remeasured, vec![String::from("X00A")]);
+        assert!(report.conflicts.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn unreadable_reports_are_rejected() -> Result<()> {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir)?;
+        // The gzip magic bytes, cut off before any of its contents.
+        let truncated = dir.join("report.txt.gz");
+        std::fs::write(&truncated, [0x1f, 0x8b, 0x08])?;
+        let catalog = Catalog::load(&dir);
+        std::fs::remove_dir_all(&dir)?;
+
+        let catalog = catalog?;
+        assert!(catalog.is_empty());
+        let rejected = catalog.diagnostics().iter().collect::<Vec<_>>();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].file, truncated.display().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn cache_is_written_on_change() -> Result<()> {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir)?;
+        let cache_path = dir.join(CACHE_FILE);
+        let listings = dir.join("listings.txt");
+        let hdr = "seller-sku\tasin1\titem-name\tproduct-id-type\titem-condition\tproduct-id\n";
+        let run = || -> Result<_> {
+            std::fs::write(&listings, format!("{hdr}aaa\tB00000NYIC\tTable\t1\t11\tB00\n"))?;
+            std::fs::write(dir.join("main_abc.json"), "{}")?;
+            let first = Catalog::load(&dir)?;
+            let written = std::fs::read_to_string(&cache_path)?;
+
+            // Padding is kept as long as nothing was read again.
+            std::fs::write(&cache_path, format!("{written} "))?;
+            let second = Catalog::load(&dir)?;
+            let kept = std::fs::read_to_string(&cache_path)?;
+
+            std::fs::write(&listings, format!("{hdr}bbb\tB00000NYID\tChairs\t1\t11\tB01\n"))?;
+            let third = Catalog::load(&dir)?;
+            let rewritten = std::fs::read_to_string(&cache_path)?;
+            Ok((first, second, third, written, kept, rewritten))
+        };
+        let ran = run();
+        std::fs::remove_dir_all(&dir)?;
+        let (first, second, third, written, kept, rewritten) = ran?;
+
+        assert!(first.diagnostics().is_empty());
+        assert!(!written.contains("main_abc.json"));
+        assert_eq!(kept, format!("{written} "));
+        assert_eq!(second.len(), first.len());
+        assert!(!rewritten.ends_with(' '));
+        assert!(third.get_by_msku("bbb").is_some());
+        Ok(())
+    }
+}
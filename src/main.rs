@@ -4,6 +4,9 @@ const CHECKDIR: &str = ".local/CHECK/";
 const STATUSDIR: &str = ".local/STATUS/";
 const LOCALDIR: &str = ".local/";
 const BOXCONTENTS: &str = "FlatBoxContents/";
+const PROFILEDIR: &str = ".local/PROFILES/";
+const MARKETDIR: &str = ".local/MARKETPLACE/";
+const STORAGERATES: &str = ".local/STORAGE_RATES.json";
 
 use anyhow::{anyhow, bail, Result};
 use eframe::{
@@ -11,15 +14,31 @@ use eframe::{
     NativeOptions,
 };
 use plaine::{
-    plan::{status::Status, Entry, Plan},
-    read::GDrivePlan,
-    utils::{self, gen_pw, TrunkFileName},
-    write, Branch, Brn,
+    catalog::{Catalog, EnrichmentReport},
+    plan::{
+        self,
+        availability::AvailabilityReport,
+        condition::Condition,
+        diff::{EnrichmentMismatch, PlanDiff, UnitChange},
+        ledger::{self, Ledger, Transaction, TransactionKind},
+        marketplace::Marketplace,
+        portion::Portion,
+        split::SplitRule,
+        status::{CheckRow, Status},
+        storage::{StorageEstimate, StorageRates},
+        summary::Summary,
+        validate::Violation,
+        Entry, Plan,
+    },
+    read::{self, Diagnostics, GDrivePlan, Import, MappingProfile, RestockPlan, COLUMNS},
+    utils::{self, gen_pw},
+    write, Branch, Brn, Fnsku,
 };
 use rfd::FileDialog;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs::read_to_string,
+    path::PathBuf,
 };
 
 fn main() {
@@ -89,15 +108,151 @@ struct CheckEntry {
 pub struct Gui {
     moved_branch_name: Option<Branch>,
     check_memory: Vec<Entry>,
+    /// The sums of `check_memory`, see [`Gui::prep_check`].
+    check_summary: Summary,
     check_entry_state: CheckEntry,
     check_entry_error: Option<anyhow::Error>,
     in_check: bool,
-    items: Vec<Entry>,
+    /// Every transaction of the current branch.
+    ledger: Ledger,
     current_branch: Option<String>,
-    branch_list: Vec<TrunkFileName>,
+    branch_list: Vec<Branch>,
     branch_statuses: HashMap<String, Status>,
     unselected: HashSet<String>,
     error_stack: Vec<anyhow::Error>,
+    enrichment: Option<EnrichmentReport>,
+    diagnostics: Option<Diagnostics>,
+    /// Only show items in this condition, `None` shows all items.
+    condition_filter: Option<Option<Condition>>,
+    /// A workbook with several sheets, waiting for one to be chosen.
+    pending_workbook: Option<PendingWorkbook>,
+    profiles: Vec<MappingProfile>,
+    /// The name of the profile used for uploads, `None` is the default.
+    selected_profile: Option<String>,
+    /// Saved profiles that could not be read.
+    skipped_profiles: Diagnostics,
+    /// A profile being written, see [`Gui::show_profile_editor`].
+    profile_draft: ProfileDraft,
+    /// Over allocation found when marking the current branch for checking.
+    availability: Option<AvailabilityReport>,
+    /// The marketplace that uploads are enriched for, `None` is any.
+    marketplace: Option<Marketplace>,
+    branch_marketplaces: HashMap<Branch, Marketplace>,
+    /// Projected storage cost of the current branch.
+    storage: Option<StorageEstimate>,
+    /// Recorded on each transaction, the user running Plaine when empty.
+    operator: String,
+    /// Recorded on the next move.
+    note: String,
+    /// Two states of the current branch being compared.
+    history: Option<HistoryView>,
+    /// The open branch that the current branch would be merged into.
+    merge_target: Option<Branch>,
+    /// Enrichment that differed between the two most recently merged branches.
+    mismatches: Vec<EnrichmentMismatch>,
+    /// The rule that the current branch would be split by.
+    split_rule: Option<SplitRule>,
+    /// Part of an Fnsku waiting to be moved off the current branch.
+    partial: PartialMove,
+    /// Cases waiting to be moved off the current branch.
+    case_selection: CaseSelection,
+    /// Records of the current branch that break its ledger.
+    violations: Vec<Violation>,
+    /// Warnings of each Fnsku of the current branch, see [`CheckRow::notes`].
+    notes: BTreeMap<Fnsku, String>,
+}
+
+/// See [`Gui::show_cases`].
+#[derive(Debug, Default, Clone)]
+struct CaseSelection {
+    /// Case ids, see [`Entry::get_id`].
+    cases: BTreeSet<String>,
+    /// `None` moves to a new branch.
+    target: Option<Branch>,
+}
+
+/// See [`Gui::show_partial_move`].
+#[derive(Debug, Default, Clone)]
+struct PartialMove {
+    fnsku: Option<Fnsku>,
+    units: i32,
+    /// Move the cases listed in `cases` instead of a count of `units`.
+    by_cases: bool,
+    /// Case ids, separated by commas.
+    cases: String,
+    /// `None` moves to a new branch.
+    target: Option<Branch>,
+}
+
+/// See [`Gui::show_profile_editor`].
+#[derive(Debug, Default, Clone)]
+struct ProfileDraft {
+    name: String,
+    /// Sheet column to plan column, see [`COLUMNS`].
+    columns: Vec<(String, String)>,
+    /// Loose pack types, separated by commas.
+    loose: String,
+}
+
+impl ProfileDraft {
+    fn from_profile(profile: &MappingProfile) -> Self {
+        Self {
+            name: profile.name.clone(),
+            columns: profile.columns.clone().into_iter().collect(),
+            loose: profile.loose.join(", "),
+        }
+    }
+
+    /// Columns without a sheet column, and empty pack types, are left out.
+    fn to_profile(&self) -> MappingProfile {
+        let columns = self
+            .columns
+            .iter()
+            .filter(|(from, _)| !from.trim().is_empty())
+            .map(|(from, to)| (from.trim().to_string(), to.clone()));
+        let loose = self
+            .loose
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty());
+        MappingProfile {
+            name: self.name.trim().to_string(),
+            columns: columns.collect(),
+            loose: loose.map(String::from).collect(),
+        }
+    }
+}
+
+/// See [`Gui::show_history`].
+#[derive(Debug, Clone)]
+struct HistoryView {
+    /// Each state is the branch after that many transactions.
+    from: usize,
+    to: usize,
+    /// The changes from `from` to `to`, kept until either is chosen again.
+    diff: PlanDiff,
+    /// Names each state, see [`Gui::state_label`].
+    labels: Vec<String>,
+}
+
+impl HistoryView {
+    fn new(ledger: &Ledger, labels: Vec<String>, from: usize, to: usize) -> Self {
+        let diff = ledger.upto(from).diff(&ledger.upto(to));
+        Self {
+            from,
+            to,
+            diff,
+            labels,
+        }
+    }
+}
+
+/// See [`Gui::show_sheet_chooser`].
+#[derive(Debug)]
+struct PendingWorkbook {
+    path: PathBuf,
+    sheets: Vec<String>,
+    selected: usize,
 }
 
 impl Gui {
@@ -108,8 +263,17 @@ impl Gui {
     /// * Unselected Items.
     /// * Branch statuses
     fn refresh(&mut self) {
-        if let Ok(trunks) = utils::gather_records(LOCALDIR) {
-            self.branch_list = trunks;
+        match Ledger::load_all(LOCALDIR) {
+            Ok(ledgers) => {
+                self.branch_list = ledgers.into_keys().collect();
+                self.branch_list.sort();
+            }
+            Err(err) => self.error_stack.push(err),
+        };
+        self.load_profiles();
+        match Marketplace::load_all(MARKETDIR) {
+            Ok(marketplaces) => self.branch_marketplaces = marketplaces,
+            Err(err) => self.error_stack.push(err),
         };
 
         self.unselected = HashSet::default();
@@ -119,32 +283,24 @@ impl Gui {
             .collect::<HashSet<String>>()
             .iter();
 
-        let dir = std::fs::read_dir(STATUSDIR).expect("Read Statuses");
-
-        let reads = dir.filter_map(|dir| dir.ok());
-        let helper = reads.map(|x| (x.path(), x.file_name()));
-        let map_helper = helper.filter_map(|(p, name)| {
-            let str = read_to_string(p).ok()?;
-            let status: Status = serde_json::from_str(&str).ok()?;
-            let (branch, _) = name.to_str()?.split_once('_')?;
-
-            Some((branch.to_string(), status))
-        });
-        let mut hm: HashMap<String, Status> = HashMap::default();
-        let mut sorted = map_helper.collect::<Vec<_>>();
-
-        sorted.sort();
-        sorted.into_iter().for_each(|(bn, status)| {
-            hm.insert(bn, status);
-        });
+        let hm = Status::load_all(STATUSDIR).expect("Read Statuses");
         if let Some(ref branch) = &self.current_branch.clone() {
             self.switch_to_branch(branch);
         };
         self.branch_statuses = hm;
         self.check_memory = Vec::default();
+        self.check_summary = Summary::default();
         self.check_entry_state = CheckEntry::default();
         self.in_check = false;
         self.moved_branch_name = None;
+        self.availability = None;
+        self.storage = None;
+        self.history = None;
+        self.merge_target = None;
+        self.mismatches = Vec::default();
+        self.split_rule = None;
+        self.partial = PartialMove::default();
+        self.case_selection = CaseSelection::default();
     }
 
     /// Shows the branch list in the given UI.
@@ -152,20 +308,10 @@ impl Gui {
         if ui.button("Refresh").clicked() {
             self.refresh();
         };
-        let branchs = self.branch_list.clone();
-
-        // Get the branches from memory
-        let uniques = branchs
-            .iter()
-            .filter_map(|b| b.split_once('_'))
-            .map(|(n, _)| n)
-            .collect::<HashSet<_>>();
-
-        let mut vex = uniques.clone().into_iter().collect::<Vec<_>>();
-        vex.sort();
         let istatus = self.branch_statuses.clone();
-        let prepped_pairs = vex
-            .into_iter()
+        let prepped_pairs = self
+            .branch_list
+            .iter()
             .map(|name| {
                 let status = match istatus.get(name) {
                     Some(x) => x.to_owned(),
@@ -198,27 +344,30 @@ impl Gui {
         // Remove client from action state whenever switching
 
         self.in_check = false;
-        let current_branch_list = &mut self.branch_list;
-        let incoming_branch = current_branch_list.iter().filter(|x| {
-            let Some((json_file_prefix, _)) = x.split_once('_') else {
-                return false;
-            };
-            json_file_prefix.eq(brn)
-        });
+        self.storage = None;
+        self.history = None;
+        self.case_selection = CaseSelection::default();
 
         // Set the current branch in self.
         self.current_branch = Some(brn.to_string());
-        let file_names = incoming_branch.map(|filename| format!("{LOCALDIR}{filename}"));
-
-        let deser_entries = file_names
-            .filter_map(|path| {
-                let entry = read_to_string(path).ok()?;
-                serde_json::from_str::<Vec<Entry>>(&entry).ok()
-            })
-            .flatten()
-            .collect();
+        match Ledger::load(LOCALDIR, brn) {
+            Ok(ledger) => {
+                self.violations = ledger.validate();
+                self.notes = CheckRow::by_fnsku(&ledger.entries())
+                    .into_iter()
+                    .map(|(fnsku, row)| (fnsku, row.notes()))
+                    .collect();
+                self.ledger = ledger;
+            }
+            Err(err) => self.error_stack.push(err),
+        };
+    }
 
-        self.items = deser_entries;
+    /// Read every saved [`MappingProfile`], keeping those that were skipped.
+    fn load_profiles(&mut self) {
+        let mut skipped = Diagnostics::default();
+        self.profiles = MappingProfile::load_all(PROFILEDIR, &mut skipped);
+        self.skipped_profiles = skipped;
     }
 
     /// Return a default instance.
@@ -234,8 +383,24 @@ impl Gui {
             };
         };
 
+        ui.horizontal(|ui| {
+            ui.label("Operator:");
+            ui.text_edit_singleline(&mut self.operator);
+        });
+        self.show_profile_chooser(ui);
+        self.show_marketplace_chooser(ui);
         if ui.button("Upload Google Drive Plan").clicked() {
             match self.legacy_button() {
+                Err(err) => self.error_stack.push(err),
+                Ok(Some(branch)) => {
+                    self.switch_to_branch(&branch);
+                    self.refresh();
+                }
+                Ok(None) => (),
+            };
+        };
+        if ui.button("Draft From Restock Report").clicked() {
+            match self.restock_button() {
                 Err(err) => self.error_stack.push(err),
                 Ok(branch) => {
                     self.switch_to_branch(&branch);
@@ -243,9 +408,263 @@ impl Gui {
                 }
             };
         };
+        self.show_sheet_chooser(ui);
+        self.show_diagnostics(ui);
+        self.show_enrichment_report(ui);
         self.actions(ui);
     }
 
+    /// Let the [`MappingProfile`] used for uploads be chosen.
+    fn show_profile_chooser(&mut self, ui: &mut Ui) {
+        if !self.profiles.is_empty() {
+            let selected = &mut self.selected_profile;
+            let text = selected.clone().unwrap_or_else(|| String::from("Default"));
+            egui::ComboBox::from_label("Column Profile")
+                .selected_text(text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(selected, None, "Default");
+                    for profile in self.profiles.iter() {
+                        let name = Some(profile.name.clone());
+                        ui.selectable_value(selected, name, &profile.name);
+                    }
+                });
+        };
+        if !self.skipped_profiles.is_empty() {
+            let title = format!("Skipped Profiles ({})", self.skipped_profiles.len());
+            ui.collapsing(title, |ui| {
+                for rejection in self.skipped_profiles.iter() {
+                    ui.label(format!("{}: {}", rejection.file, rejection.reason));
+                }
+            });
+        };
+        self.show_profile_editor(ui);
+    }
+
+    /// Let a [`MappingProfile`] be written and saved, or an existing one changed.
+    ///
+    /// A saved profile is selected for uploads.
+    fn show_profile_editor(&mut self, ui: &mut Ui) {
+        let selected = self.profile();
+        let draft = &mut self.profile_draft;
+        let mut save = false;
+        ui.collapsing("Edit Column Profile", |ui| {
+            if ui.button("Start From Selected Profile").clicked() {
+                *draft = ProfileDraft::from_profile(&selected);
+            };
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut draft.name);
+            });
+            let mut removed = None;
+            Grid::new("profile-columns").striped(true).show(ui, |ui| {
+                ui.label("Sheet Column");
+                ui.label("Plan Column");
+                ui.end_row();
+                for (idx, (from, to)) in draft.columns.iter_mut().enumerate() {
+                    ui.text_edit_singleline(from);
+                    egui::ComboBox::from_id_source(("profile-column", idx))
+                        .selected_text(to.as_str())
+                        .show_ui(ui, |ui| {
+                            for column in COLUMNS {
+                                ui.selectable_value(to, column.to_string(), column);
+                            }
+                        });
+                    if ui.button("Remove").clicked() {
+                        removed = Some(idx);
+                    };
+                    ui.end_row();
+                }
+            });
+            if let Some(idx) = removed {
+                draft.columns.remove(idx);
+            };
+            if ui.button("Add Column").clicked() {
+                draft.columns.push((String::new(), COLUMNS[1].to_string()));
+            };
+            ui.horizontal(|ui| {
+                ui.label("Loose Pack Types:");
+                ui.text_edit_singleline(&mut draft.loose);
+            });
+            save = ui.button("Save Profile").clicked();
+        });
+        if !save {
+            return;
+        };
+        let profile = self.profile_draft.to_profile();
+        match profile.save(PROFILEDIR) {
+            Ok(_) => {
+                self.load_profiles();
+                self.selected_profile = Some(profile.name);
+            }
+            Err(err) => self.error_stack.push(err),
+        };
+    }
+
+    /// Let the [`Marketplace`] that uploads are enriched for be chosen.
+    fn show_marketplace_chooser(&mut self, ui: &mut Ui) {
+        let selected = &mut self.marketplace;
+        let text = selected.map(|x| x.to_string());
+        egui::ComboBox::from_label("Marketplace")
+            .selected_text(text.unwrap_or_else(|| String::from("Any")))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(selected, None, "Any");
+                for marketplace in Marketplace::ALL {
+                    let name = format!("{marketplace} ({})", marketplace.currency());
+                    ui.selectable_value(selected, Some(marketplace), name);
+                }
+            });
+    }
+
+    /// Returns the [`MappingProfile`] chosen for uploads.
+    fn profile(&self) -> MappingProfile {
+        self.profiles
+            .iter()
+            .find(|x| Some(&x.name) == self.selected_profile.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Let a sheet of the pending workbook be chosen and imported.
+    fn show_sheet_chooser(&mut self, ui: &mut Ui) {
+        let Some(pending) = &mut self.pending_workbook else {
+            return;
+        };
+        let mut import = false;
+        let mut cancel = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Sheet")
+                .selected_text(&pending.sheets[pending.selected])
+                .show_ui(ui, |ui| {
+                    for (idx, sheet) in pending.sheets.iter().enumerate() {
+                        ui.selectable_value(&mut pending.selected, idx, sheet);
+                    }
+                });
+            import = ui.button("Import Sheet").clicked();
+            cancel = ui.button("Cancel").clicked();
+        });
+        if cancel {
+            self.pending_workbook = None;
+        };
+        if !import {
+            return;
+        };
+        let Some(pending) = self.pending_workbook.take() else {
+            return;
+        };
+        let sheet = &pending.sheets[pending.selected];
+        let profile = self.profile();
+        let note = format!("{}[{sheet}]", pending.path.display());
+        let imported =
+            GDrivePlan::proc_from_sheet(&pending.path, sheet, &profile, self.marketplace)
+                .and_then(|import| self.write_import(import, Status::Open, &note));
+        match imported {
+            Err(err) => self.error_stack.push(err),
+            Ok(branch) => {
+                self.switch_to_branch(&branch);
+                self.refresh();
+            }
+        };
+    }
+
+    /// Show the rows that were rejected by the most recent upload.
+    fn show_diagnostics(&mut self, ui: &mut Ui) {
+        let Some(diagnostics) = &self.diagnostics else {
+            return;
+        };
+        let warnings = diagnostics.warnings().count();
+        if diagnostics.is_empty() && warnings == 0 {
+            return;
+        };
+        let title = format!(
+            "Import Diagnostics ({} rejected, {warnings} warnings)",
+            diagnostics.len()
+        );
+        let mut export = false;
+        ui.collapsing(title, |ui| {
+            if warnings > 0 {
+                ui.label("Imported with warnings:");
+                Grid::new("import-warnings").striped(true).show(ui, |ui| {
+                    diagnostics.warnings().for_each(|warning| {
+                        let line = warning.line.map(|x| x.to_string());
+                        ui.label(&warning.file);
+                        ui.label(line.unwrap_or_default());
+                        ui.label(warning.column.as_deref().unwrap_or_default());
+                        ui.label(&warning.reason);
+                        ui.end_row();
+                    });
+                });
+            };
+            export = ui.button("Export CSV").clicked();
+            Grid::new("import-diagnostics")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("File");
+                    ui.label("Line");
+                    ui.label("Column");
+                    ui.label("Reason");
+                    ui.end_row();
+                    diagnostics.iter().for_each(|rejection| {
+                        let line = rejection.line.map(|x| x.to_string());
+                        ui.label(&rejection.file);
+                        ui.label(line.unwrap_or_default());
+                        ui.label(rejection.column.as_deref().unwrap_or_default());
+                        ui.label(&rejection.reason);
+                        ui.end_row();
+                    });
+                });
+        });
+        if !export {
+            return;
+        };
+        let picked_file = FileDialog::new()
+            .add_filter("csv", &["csv"])
+            .set_file_name("diagnostics.csv")
+            .save_file();
+        if let Some(path) = picked_file {
+            if let Err(err) = write::write_diagnostics_csv(diagnostics, path) {
+                self.error_stack.push(err);
+            };
+        };
+    }
+
+    /// Show the conflicts and unmatched items of the most recent upload.
+    fn show_enrichment_report(&mut self, ui: &mut Ui) {
+        let Some(report) = &self.enrichment else {
+            return;
+        };
+        if report.is_empty() {
+            return;
+        };
+        ui.collapsing("Enrichment Report", |ui| {
+            if !report.unmatched.is_empty() {
+                ui.label("Not found in any report:");
+                ui.label(report.unmatched.join(", "));
+            };
+            if !report.remeasured.is_empty() {
+                ui.label("Remeasured by Amazon:");
+                ui.label(report.remeasured.join(", "));
+            };
+            Grid::new("enrichment-conflicts").striped(true).show(ui, |ui| {
+                ui.label("Item");
+                ui.label("Field");
+                ui.label("Used");
+                ui.label("From");
+                ui.label("Instead Of");
+                ui.label("From");
+                ui.end_row();
+                report.conflicts.iter().for_each(|conflict| {
+                    ui.label(&conflict.key);
+                    ui.label(format!("{:?}", conflict.field));
+                    ui.label(&conflict.kept.value);
+                    ui.label(conflict.kept.source.to_string());
+                    ui.label(&conflict.replaced.value);
+                    ui.label(conflict.replaced.source.to_string());
+                    ui.end_row();
+                });
+            });
+        });
+    }
+
     fn actions(&mut self, ui: &mut Ui) -> Option<()> {
         let current_branch = &self.current_branch.clone()?;
         let status_map = self.branch_statuses.clone();
@@ -254,34 +673,124 @@ impl Gui {
         ui.separator();
         ui.label("You are on:");
         ui.label(current_branch);
+        if let Some(marketplace) = self.branch_marketplaces.get(current_branch) {
+            ui.label(format!(
+                "Marketplace: {marketplace} ({})",
+                marketplace.currency()
+            ));
+        };
         ui.separator();
 
+        self.show_ledger(ui);
+        self.show_history(ui);
         if let Some(recent_moved_branch) = &self.moved_branch_name {
             ui.separator();
             ui.label("You recently moved items to:");
             ui.label(recent_moved_branch);
             ui.separator();
         }
+        self.show_mismatches(ui);
+        self.show_violations(ui);
 
+        if matches!(current_status, Status::Draft) {
+            if ui.button("Open Draft").clicked() {
+                match Status::Open.permits(&self.ledger) {
+                    Ok(_) => {
+                        Status::mark(&Status::Open, STATUSDIR, current_branch).ok()?;
+                        self.refresh()
+                    }
+                    Err(err) => self.error_stack.push(err),
+                };
+            };
+            self.show_current_branch_contents(ui);
+        };
         if matches!(current_status, Status::Open) {
-            let make_upload_file = ui.button("Make Upload File");
-            let remove_unselected = ui.button("Move unselected to new branch");
+            let (make_upload_file, make_check_file) = ui
+                .horizontal(|ui| (ui.button("Make Upload File"), ui.button("Make Check File")))
+                .inner;
+            let remove_unselected = ui.horizontal(|ui| {
+                let clicked = ui.button("Move unselected to new branch");
+                ui.label("Note:");
+                ui.text_edit_singleline(&mut self.note);
+                clicked
+            });
+            let remove_unselected = remove_unselected.inner;
+            let merge = self.show_merge_target(ui, current_branch);
+            let split = self.show_split_rule(ui);
+            let partial = self.show_partial_move(ui, current_branch);
             let mark_for_checking = ui.button("Mark CURRENT branch for checking");
 
             if make_upload_file.clicked() {
-                let items = self.items.clone();
+                let items = self.ledger.entries();
                 write::write_upload_txt(items, current_branch).expect("Upload File Write");
             };
+            if make_check_file.clicked() {
+                // A check file is still useful without storage costs.
+                let estimate = match self.estimate_storage(current_branch) {
+                    Ok(estimate) => Some(estimate),
+                    Err(err) => {
+                        self.error_stack.push(err);
+                        None
+                    }
+                };
+                let items = self.ledger.entries();
+                let name = current_branch.to_string();
+                if let Err(err) = write::write_check_file(items, name, estimate.as_ref()) {
+                    self.error_stack.push(err.into());
+                };
+            };
             if remove_unselected.clicked() {
                 let new_branch_name = self.new_branch_from_unselected().ok()?;
                 self.moved_branch_name = Some(new_branch_name);
+                self.note.clear();
+            };
+            if let Some(target) = merge {
+                self.merge_into(current_branch, target);
+            };
+            if partial {
+                let moved = self.move_partial(current_branch);
+                self.finish_move(moved);
+            };
+            if let Some(rule) = split {
+                match self.split_by_rule(current_branch, rule) {
+                    Ok(branches) => {
+                        self.refresh();
+                        self.note.clear();
+                        self.moved_branch_name = Some(branches.join(", "));
+                    }
+                    Err(err) => self.error_stack.push(err),
+                };
             };
             if mark_for_checking.clicked() {
-                Status::mark(&Status::Check, STATUSDIR, current_branch).ok()?;
-                self.refresh()
+                let permitted = Status::Check.permits(&self.ledger);
+                match permitted.and_then(|_| self.check_availability()) {
+                    Ok(report) if report.over_allocated_in(current_branch).is_empty() => {
+                        Status::mark(&Status::Check, STATUSDIR, current_branch).ok()?;
+                        self.refresh()
+                    }
+                    Ok(report) => self.availability = Some(report),
+                    Err(err) => self.error_stack.push(err),
+                };
+            };
+            if self.show_availability(ui, current_branch) {
+                match Status::Check.permits(&self.ledger) {
+                    Ok(_) => {
+                        Status::mark(&Status::Check, STATUSDIR, current_branch).ok()?;
+                        self.refresh()
+                    }
+                    Err(err) => self.error_stack.push(err),
+                };
+            };
+            self.show_storage_estimate(ui, current_branch);
+            if self.show_cases(ui, current_branch) {
+                let moved = self.move_cases(current_branch);
+                self.finish_move(moved);
             };
             self.show_current_branch_contents(ui);
         };
+        if matches!(current_status, Status::Closed) {
+            ui.label("Closed, every unit was moved back out of this branch.");
+        };
         // CLOSED SHIPMENT
         if matches!(current_status, Status::Check) && ui.button("Start Check").clicked() {
             self.prep_check().ok()?;
@@ -292,6 +801,607 @@ impl Gui {
         Some(())
     }
 
+    /// Show every transaction of the current branch, oldest first.
+    ///
+    /// Each transaction can be opened in the history panel, to compare the
+    /// branch before and after it.
+    fn show_ledger(&mut self, ui: &mut Ui) {
+        let transactions = self.ledger.transactions();
+        if transactions.is_empty() {
+            return;
+        };
+        let mut compare = None;
+        let mut revert = None;
+        let title = format!("Ledger ({} transactions)", transactions.len());
+        ui.collapsing(title, |ui| {
+            Grid::new("ledger-grid").striped(true).show(ui, |ui| {
+                ui.label("Time (UTC)");
+                ui.label("Kind");
+                ui.label("Units");
+                ui.label("Operator");
+                ui.label("Branch");
+                ui.label("Note");
+                ui.end_row();
+                for (idx, transaction) in transactions.iter().enumerate() {
+                    let link = transaction.link.as_ref().map(|x| x.branch.as_str());
+                    ui.label(utils::format_time(transaction.time));
+                    ui.label(transaction.kind.to_string());
+                    ui.label(transaction.units().to_string());
+                    ui.label(&transaction.operator);
+                    ui.label(link.unwrap_or_default());
+                    ui.label(&transaction.note);
+                    if ui.small_button("Compare").clicked() {
+                        compare = Some(idx);
+                    };
+                    let is_move = matches!(
+                        transaction.kind,
+                        TransactionKind::MoveOut | TransactionKind::MoveIn
+                    );
+                    if is_move && link.is_some() && ui.small_button("Revert").clicked() {
+                        revert = Some(transaction.id.clone());
+                    };
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(idx) = compare {
+            let states = 0..=self.ledger.transactions().len();
+            let labels = states.map(|x| self.state_label(x)).collect();
+            self.history = Some(HistoryView::new(&self.ledger, labels, idx, idx + 1));
+        };
+        if let Some(id) = revert {
+            let branch = self.ledger.branch().to_owned();
+            let reverted = ledger::revert_move(
+                LOCALDIR,
+                STATUSDIR,
+                &branch,
+                &id,
+                &self.operator,
+                &self.note,
+            );
+            match reverted {
+                Ok(_) => self.note.clear(),
+                Err(err) => self.error_stack.push(err),
+            };
+            self.refresh();
+        };
+    }
+
+    /// Show the open branches that the current branch can be merged into.
+    ///
+    /// Returns the chosen branch once the merge is confirmed.
+    fn show_merge_target(&mut self, ui: &mut Ui, current: &str) -> Option<Branch> {
+        let targets = self.open_branches_beside(current);
+        if targets.is_empty() {
+            return None;
+        };
+        let mut merge = false;
+        ui.horizontal(|ui| {
+            let selected = self.merge_target.as_deref().unwrap_or("Choose a branch");
+            egui::ComboBox::from_label("Merge into")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for target in targets {
+                        ui.selectable_value(&mut self.merge_target, Some(target.clone()), target);
+                    }
+                });
+            merge = ui.button("Merge CURRENT branch").clicked();
+        });
+        merge.then(|| self.merge_target.clone()).flatten()
+    }
+
+    /// Returns every other open branch of the same marketplace as `current`.
+    fn open_branches_beside(&self, current: &str) -> Vec<Branch> {
+        let marketplace = self.branch_marketplaces.get(current);
+        self.branch_list
+            .iter()
+            .filter(|branch| {
+                let status = self.branch_statuses.get(*branch).copied();
+                let same_market = self.branch_marketplaces.get(*branch) == marketplace;
+                *branch != current && status.unwrap_or_default() == Status::Open && same_market
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Show a count of units or a list of cases of a single Fnsku to move.
+    ///
+    /// Returns true once the move is confirmed.
+    fn show_partial_move(&mut self, ui: &mut Ui, current: &str) -> bool {
+        let targets = self.open_branches_beside(current);
+        let summary = self.ledger.summary();
+        let partial = &mut self.partial;
+        let most = partial
+            .fnsku
+            .as_deref()
+            .map(|x| summary.units_of(x))
+            .unwrap_or_default();
+        let mut clicked = false;
+        ui.horizontal(|ui| {
+            let selected = partial.fnsku.as_deref().unwrap_or("Choose an Fnsku");
+            egui::ComboBox::from_label("Move part of")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for sum in summary.sums().iter().filter(|x| x.get_units() > 0) {
+                        let fnsku = sum.get_fnsku();
+                        ui.selectable_value(&mut partial.fnsku, Some(fnsku.to_string()), fnsku);
+                    }
+                });
+            ui.radio_value(&mut partial.by_cases, false, "Units");
+            ui.radio_value(&mut partial.by_cases, true, "Case Ids");
+            match partial.by_cases {
+                true => ui.text_edit_singleline(&mut partial.cases),
+                false => ui.add(egui::DragValue::new(&mut partial.units).clamp_range(1..=most)),
+            };
+            let target = partial.target.as_deref().unwrap_or("New branch");
+            egui::ComboBox::from_label("To")
+                .selected_text(target)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut partial.target, None, "New branch");
+                    for target in targets {
+                        ui.selectable_value(&mut partial.target, Some(target.clone()), target);
+                    }
+                });
+            clicked = ui.button("Move Part").clicked();
+        });
+        clicked
+    }
+
+    /// Move the part of an Fnsku chosen in [`Self::show_partial_move`].
+    ///
+    /// Returns the branch that the part was moved to.
+    fn move_partial(&self, current: &str) -> Result<Branch> {
+        let partial = &self.partial;
+        let fnsku = partial
+            .fnsku
+            .as_deref()
+            .ok_or(anyhow!("Choose an Fnsku to move"))?;
+        let portion = match partial.by_cases {
+            true => {
+                let ids = partial.cases.split(',').map(str::trim);
+                Portion::Cases(ids.filter(|x| !x.is_empty()).map(String::from).collect())
+            }
+            false => Portion::Units(partial.units),
+        };
+        let entries = self.ledger.portion(fnsku, &portion)?;
+        self.move_to_branch(current, partial.target.as_ref(), entries)
+    }
+
+    /// Show the branch that entries were `moved` to, or why they were not.
+    fn finish_move(&mut self, moved: Result<Branch>) {
+        match moved {
+            Ok(branch) => {
+                self.refresh();
+                self.note.clear();
+                self.moved_branch_name = Some(branch);
+            }
+            Err(err) => self.error_stack.push(err),
+        };
+    }
+
+    /// Move `entries` from `current` to `target`, or to a new open branch.
+    ///
+    /// Returns the branch that the entries were moved to.
+    fn move_to_branch(
+        &self,
+        current: &str,
+        target: Option<&Branch>,
+        entries: Vec<Entry>,
+    ) -> Result<Branch> {
+        let branch = target.cloned().unwrap_or_else(gen_pw);
+        let (move_out, move_in) = ledger::move_entries(
+            LOCALDIR,
+            current,
+            &branch,
+            entries,
+            &self.operator,
+            &self.note,
+        )?;
+        if target.is_none() {
+            let moved = [&move_out, &move_in];
+            ledger::mark_written(LOCALDIR, STATUSDIR, &moved, &[(Status::Open, &branch)])?;
+            if let Some(marketplace) = self.branch_marketplaces.get(current) {
+                Marketplace::mark(marketplace, MARKETDIR, &branch)?;
+            };
+        };
+        Ok(branch)
+    }
+
+    /// Show every case of the current branch that still holds units.
+    ///
+    /// Each case can be selected whole. Returns true once the selected cases
+    /// should be moved.
+    fn show_cases(&mut self, ui: &mut Ui, current: &str) -> bool {
+        let targets = self.open_branches_beside(current);
+        let cases = self.ledger.summary().cases();
+        let selection = &mut self.case_selection;
+        let mut clicked = false;
+        let title = format!(
+            "Cases ({}, {} selected)",
+            cases.len(),
+            selection.cases.len()
+        );
+        ui.collapsing(title, |ui| {
+            ui.horizontal(|ui| {
+                let target = selection.target.as_deref().unwrap_or("New branch");
+                egui::ComboBox::from_label("Move selected cases to")
+                    .selected_text(target)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut selection.target, None, "New branch");
+                        for target in targets {
+                            let label = target.clone();
+                            ui.selectable_value(&mut selection.target, Some(target), label);
+                        }
+                    });
+                clicked = ui.button("Move Cases").clicked();
+                if ui.button("Clear Selection").clicked() {
+                    selection.cases.clear();
+                };
+            });
+            Grid::new("case-grid").striped(true).show(ui, |ui| {
+                ui.label("");
+                ui.label("Case");
+                ui.label("Fnskus");
+                ui.label("Units");
+                ui.label("Pounds");
+                ui.label("Dimensions");
+                ui.end_row();
+                for (id, entries) in cases {
+                    let checked = selection.cases.contains(id);
+                    let mut check = checked;
+                    ui.checkbox(&mut check, "");
+                    let fnskus = entries.iter().map(|x| x.get_fnsku()).collect::<Vec<_>>();
+                    let pounds = entries.iter().filter_map(|x| *x.get_total_pounds());
+                    let dims = entries.iter().find_map(|x| x.get_case_dimensions());
+                    ui.label(id);
+                    ui.label(fnskus.join(", "));
+                    ui.label(entries.units().to_string());
+                    ui.label(
+                        pounds
+                            .reduce(f32::max)
+                            .map(|x| x.to_string())
+                            .unwrap_or_default(),
+                    );
+                    ui.label(
+                        dims.map(|x| format!("{} x {} x {}", x[0], x[1], x[2]))
+                            .unwrap_or_default(),
+                    );
+                    ui.end_row();
+                    match (checked, check) {
+                        (false, true) => selection.cases.insert(id.clone()),
+                        (true, false) => selection.cases.remove(id),
+                        _ => false,
+                    };
+                }
+            });
+        });
+        clicked
+    }
+
+    /// Move every case chosen in [`Self::show_cases`], whole.
+    ///
+    /// Returns the branch that the cases were moved to.
+    fn move_cases(&self, current: &str) -> Result<Branch> {
+        let selection = &self.case_selection;
+        let entries = self.ledger.take_cases(&selection.cases)?;
+        self.move_to_branch(current, selection.target.as_ref(), entries)
+    }
+
+    /// Show the rules that the current branch can be split by.
+    ///
+    /// Returns the chosen rule once the split is confirmed.
+    fn show_split_rule(&mut self, ui: &mut Ui) -> Option<SplitRule> {
+        let mut split = false;
+        ui.horizontal(|ui| {
+            let selected = self.split_rule.map(|x| x.to_string());
+            egui::ComboBox::from_label("Split by")
+                .selected_text(selected.unwrap_or_else(|| String::from("Choose a rule")))
+                .show_ui(ui, |ui| {
+                    for rule in SplitRule::ALL {
+                        ui.selectable_value(&mut self.split_rule, Some(rule), rule.to_string());
+                    }
+                });
+            if let Some(SplitRule::Weight { pounds }) = &mut self.split_rule {
+                ui.add(
+                    egui::DragValue::new(pounds)
+                        .clamp_range(1.0..=150.0)
+                        .suffix(" lb"),
+                );
+            };
+            split = ui.button("Split CURRENT branch").clicked();
+        });
+        split.then_some(self.split_rule).flatten()
+    }
+
+    /// Move each group of `current` to a new branch, closing `current`.
+    ///
+    /// Returns the names of the new branches.
+    fn split_by_rule(&self, current: &str, rule: SplitRule) -> Result<Vec<Branch>> {
+        let groups = self
+            .ledger
+            .split_by(rule)
+            .into_iter()
+            .map(|group| (gen_pw(), group))
+            .collect::<Vec<_>>();
+        let branches = groups.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+        ledger::split_branch(
+            LOCALDIR,
+            STATUSDIR,
+            current,
+            groups,
+            &self.operator,
+            &self.note,
+        )?;
+        if let Some(marketplace) = self.branch_marketplaces.get(current) {
+            for branch in &branches {
+                Marketplace::mark(marketplace, MARKETDIR, branch)?;
+            }
+        };
+        Ok(branches)
+    }
+
+    /// Merge every remaining entry of `current` into `target`, closing `current`.
+    fn merge_into(&mut self, current: &str, target: Branch) {
+        let merged = ledger::merge_branches(
+            LOCALDIR,
+            STATUSDIR,
+            current,
+            &target,
+            &self.operator,
+            &self.note,
+        );
+        match merged {
+            Ok(merge) => {
+                self.refresh();
+                self.note.clear();
+                self.moved_branch_name = Some(target);
+                self.mismatches = merge.mismatches;
+            }
+            Err(err) => self.error_stack.push(err),
+        };
+    }
+
+    /// Show every record of the current branch that breaks its ledger.
+    fn show_violations(&self, ui: &mut Ui) {
+        if self.violations.is_empty() {
+            return;
+        };
+        ui.label("The ledger of this branch is broken, its status cannot change:");
+        for violation in &self.violations {
+            ui.label(violation.to_string());
+        }
+        ui.separator();
+    }
+
+    /// Show the enrichment that differed between the two merged branches.
+    fn show_mismatches(&self, ui: &mut Ui) {
+        if self.mismatches.is_empty() {
+            return;
+        };
+        ui.label("These Fnskus were enriched differently on each branch:");
+        Grid::new("mismatch-grid").striped(true).show(ui, |ui| {
+            ui.label("Fnsku");
+            ui.label("Field");
+            ui.label("Merged Into");
+            ui.label("Merged From");
+            ui.end_row();
+            for mismatch in &self.mismatches {
+                ui.label(&mismatch.fnsku);
+                ui.label(format!("{:?}", mismatch.field));
+                ui.label(&mismatch.ours);
+                ui.label(&mismatch.theirs);
+                ui.end_row();
+            }
+        });
+        ui.separator();
+    }
+
+    /// Returns a label for the state of the current branch after `count`
+    /// transactions.
+    fn state_label(&self, count: usize) -> String {
+        match count.checked_sub(1) {
+            None => String::from("Empty branch"),
+            Some(idx) => {
+                let transaction = &self.ledger.transactions()[idx];
+                let time = utils::format_time(transaction.time);
+                format!("{count}. {time} {}", transaction.kind)
+            }
+        }
+    }
+
+    /// Show the units that changed between two states of the current branch.
+    fn show_history(&mut self, ui: &mut Ui) {
+        let Some(mut view) = self.history.take() else {
+            return;
+        };
+        let (mut from, mut to) = (view.from, view.to);
+        let mut close = false;
+        ui.separator();
+        ui.horizontal(|ui| {
+            for (name, state) in [("From", &mut from), ("To", &mut to)] {
+                egui::ComboBox::from_label(name)
+                    .selected_text(&view.labels[*state])
+                    .show_ui(ui, |ui| {
+                        for (idx, label) in view.labels.iter().enumerate() {
+                            ui.selectable_value(state, idx, label);
+                        }
+                    });
+            }
+            close = ui.button("Close History").clicked();
+        });
+        if (from, to) != (view.from, view.to) {
+            view = HistoryView::new(&self.ledger, view.labels, from, to);
+        };
+        let diff = &view.diff;
+        if diff.is_empty() {
+            ui.label("No units changed.");
+        };
+        let change = |ui: &mut Ui, x: &UnitChange| {
+            ui.label(x.before.to_string());
+            ui.label(x.after.to_string());
+            ui.label(format!("{:+}", x.delta()));
+        };
+        ui.collapsing(format!("Fnskus ({})", diff.fnskus.len()), |ui| {
+            Grid::new("history-fnskus").striped(true).show(ui, |ui| {
+                ui.label("Fnsku");
+                ui.label("Before");
+                ui.label("After");
+                ui.label("Change");
+                ui.end_row();
+                for (fnsku, units) in diff.fnskus.iter() {
+                    ui.label(fnsku);
+                    change(ui, units);
+                    ui.end_row();
+                }
+            });
+        });
+        ui.collapsing(format!("Cases ({})", diff.cases.len()), |ui| {
+            Grid::new("history-cases").striped(true).show(ui, |ui| {
+                ui.label("Case");
+                ui.label("Fnsku");
+                ui.label("Before");
+                ui.label("After");
+                ui.label("Change");
+                ui.end_row();
+                for (case, fnskus) in diff.cases.iter() {
+                    for (fnsku, units) in fnskus.iter() {
+                        ui.label(case);
+                        ui.label(fnsku);
+                        change(ui, units);
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+        ui.separator();
+        self.history = (!close).then_some(view);
+    }
+
+    /// Compare the units planned on every active branch with availability.
+    ///
+    /// Only branches of the same marketplace as the current branch share
+    /// inventory with it.
+    fn check_availability(&self) -> Result<AvailabilityReport> {
+        let current = self
+            .current_branch
+            .as_ref()
+            .ok_or(anyhow!("Not on a branch"))?;
+        let marketplace = self.branch_marketplaces.get(current).copied();
+        let statuses = Status::load_all(STATUSDIR)?;
+        let branches = plan::load_branches(LOCALDIR)?;
+        let active = branches.iter().filter(|(branch, _)| {
+            let status = statuses.get(*branch).copied().unwrap_or_default();
+            let same_market = self.branch_marketplaces.get(*branch).copied() == marketplace;
+            status.is_active() && same_market
+        });
+        let catalog = Catalog::load_for(LOCALDIR, marketplace)?;
+        Ok(AvailabilityReport::new(active, &catalog))
+    }
+
+    /// Show the over allocated Fnskus of `branch`.
+    ///
+    /// Returns true if the branch should be marked for checking regardless.
+    fn show_availability(&mut self, ui: &mut Ui, branch: &str) -> bool {
+        let Some(report) = self.availability.clone() else {
+            return false;
+        };
+        let mut mark_anyway = false;
+        ui.separator();
+        ui.label("More units are planned than are available:");
+        Grid::new("availability-grid").striped(true).show(ui, |ui| {
+            ui.label("Fnsku");
+            ui.label("This Branch");
+            ui.label("All Branches");
+            ui.label("Available");
+            ui.label("Short");
+            ui.end_row();
+            for allocation in report.over_allocated_in(branch) {
+                let ours = allocation.branches.get(branch).copied().unwrap_or_default();
+                let available = allocation.available.map(|x| x.to_string());
+                ui.label(&allocation.fnsku);
+                ui.label(ours.to_string());
+                ui.label(allocation.planned().to_string());
+                ui.label(available.unwrap_or_else(|| String::from("Unknown")));
+                ui.label(allocation.shortfall().to_string());
+                ui.end_row();
+            }
+        });
+        ui.horizontal(|ui| {
+            mark_anyway = ui.button("Mark Anyway").clicked();
+            if ui.button("Cancel").clicked() {
+                self.availability = None;
+            };
+        });
+        ui.separator();
+        mark_anyway
+    }
+
+    /// Estimate the monthly storage cost of the current branch.
+    fn estimate_storage(&self, branch: &str) -> Result<StorageEstimate> {
+        let marketplace = self.branch_marketplaces.get(branch).copied();
+        let catalog = Catalog::load_for(LOCALDIR, marketplace)?;
+        let rates = StorageRates::load(STORAGERATES)?;
+        StorageEstimate::new(&self.ledger, &catalog, &rates, marketplace)
+    }
+
+    /// Show the projected storage cost of `branch`, once it is estimated.
+    fn show_storage_estimate(&mut self, ui: &mut Ui, branch: &str) {
+        if ui.button("Estimate Storage Cost").clicked() {
+            match self.estimate_storage(branch) {
+                Ok(estimate) => self.storage = Some(estimate),
+                Err(err) => self.error_stack.push(err),
+            };
+        };
+        let Some(estimate) = &self.storage else {
+            return;
+        };
+        let currency = &estimate.currency;
+        let title = format!(
+            "Storage Estimate ({:.2} off peak, {:.2} peak, {:.2} charged {currency} a month)",
+            estimate.total_off_peak(),
+            estimate.total_peak(),
+            estimate.total_charged(),
+        );
+        let mut export = false;
+        ui.collapsing(title, |ui| {
+            export = ui.button("Export CSV").clicked();
+            let unknown = estimate.unknown().len();
+            if unknown > 0 {
+                ui.label(format!("{unknown} Fnskus have no volume or size tier."));
+            };
+            Grid::new("storage-grid").striped(true).show(ui, |ui| {
+                ui.label("Fnsku");
+                ui.label("Units");
+                ui.label("Size Tier");
+                ui.label("Cubic Feet");
+                ui.label("Off Peak");
+                ui.label("Peak");
+                ui.label("Last Charged");
+                ui.end_row();
+                let money = |x: Option<f32>| x.map(|x| format!("{x:.2}")).unwrap_or_default();
+                for cost in estimate.costs.iter() {
+                    ui.label(&cost.fnsku);
+                    ui.label(cost.units.to_string());
+                    ui.label(cost.size_tier.as_deref().unwrap_or_default());
+                    ui.label(
+                        cost.cubic_feet
+                            .map(|x| format!("{x:.3}"))
+                            .unwrap_or_default(),
+                    );
+                    ui.label(money(cost.off_peak));
+                    ui.label(money(cost.peak));
+                    ui.label(money(cost.charged));
+                    ui.end_row();
+                }
+            });
+        });
+        if export {
+            if let Err(err) = write::write_storage_estimate_csv(estimate, branch) {
+                self.error_stack.push(err);
+            };
+        };
+    }
+
     /// Pulls the local check entries into memory
     fn prep_check(&mut self) -> Result<()> {
         let our_branch = self
@@ -314,17 +1424,14 @@ impl Gui {
         our_checks.for_each(|entries| {
             self.check_memory.extend_from_slice(&entries);
         });
+        self.check_summary = self.check_memory.summarize();
         self.in_check = true;
         Ok(())
     }
 
     /// Runs the check in the central panel.
     fn run_check(&mut self, branch: Branch, ui: &mut Ui) {
-        let mut memory = self.check_memory.get_as_sums();
         let current_check_item = &mut self.check_entry_state;
-        let _ = &self.items.clone();
-
-        memory.sort_unstable_by_key(|x| x.get_fnsku().to_string());
 
         ui.label("Fnsku:");
         ui.text_edit_singleline(&mut current_check_item.fnsku);
@@ -343,7 +1450,7 @@ impl Gui {
             ui.label("Scanned Fnskus");
             ui.label("Units Checked");
             ui.end_row();
-            memory.into_iter().for_each(|x| {
+            self.check_summary.sums().iter().for_each(|x| {
                 ui.label(x.get_fnsku());
                 ui.label(x.get_units().to_string());
                 ui.end_row();
@@ -372,15 +1479,56 @@ impl Gui {
     }
 
     /// Show a file dialog so a google drive sheet can be uploaded.
-    fn legacy_button(&mut self) -> Result<Branch> {
+    ///
+    /// Workbooks with more than one sheet are held until a sheet is chosen,
+    /// in which case no branch is returned. See [`Self::show_sheet_chooser`].
+    fn legacy_button(&mut self) -> Result<Option<Branch>> {
         let picked_file = FileDialog::new()
             .pick_file()
             .ok_or_else(|| anyhow::anyhow!("Uploading Failed"))?;
 
-        let items = GDrivePlan::proc_from_path(picked_file)?;
+        if read::is_workbook(&picked_file) {
+            let sheets = read::sheet_names(&picked_file)?;
+            if sheets.len() > 1 {
+                self.pending_workbook = Some(PendingWorkbook {
+                    path: picked_file,
+                    sheets,
+                    selected: 0,
+                });
+                return Ok(None);
+            };
+        };
+        let import =
+            GDrivePlan::proc_with_profile(&picked_file, &self.profile(), self.marketplace)?;
+        let note = picked_file.display().to_string();
+        self.write_import(import, Status::Open, &note).map(Some)
+    }
+
+    /// Show a file dialog so a Restock Inventory report can be drafted.
+    fn restock_button(&mut self) -> Result<Branch> {
+        let picked_file = FileDialog::new()
+            .pick_file()
+            .ok_or_else(|| anyhow::anyhow!("Uploading Failed"))?;
+        let import = RestockPlan::proc_from_path(&picked_file)?;
+        let note = picked_file.display().to_string();
+        self.write_import(import, Status::Draft, &note)
+    }
+
+    /// Write the entries of `import` to a new branch, marked with `status`.
+    ///
+    /// The `note` of the import transaction names where it was read from.
+    fn write_import(&mut self, import: Import, status: Status, note: &str) -> Result<Branch> {
         let trunk = gen_pw();
-        items.serialize_and_write(&trunk, LOCALDIR)?;
-        Status::mark(&Status::Open, STATUSDIR, &trunk)?;
+        let transaction = Transaction::new(&trunk, TransactionKind::Import, import.entries)
+            .with_operator(&self.operator)
+            .with_note(note);
+        ledger::write_all(LOCALDIR, &[&transaction])?;
+        ledger::mark_written(LOCALDIR, STATUSDIR, &[&transaction], &[(status, &trunk)])?;
+        self.enrichment = Some(import.enrichment);
+        self.diagnostics = Some(import.diagnostics);
+        if let Some(marketplace) = import.marketplace {
+            Marketplace::mark(&marketplace, MARKETDIR, &trunk)?;
+        };
         Ok(trunk)
     }
 
@@ -393,18 +1541,24 @@ impl Gui {
             return Err(anyhow!("Everything is selected, nothing to branch"));
         };
 
-        let branch = gen_pw();
-        branching_items.serialize_and_write(&branch, LOCALDIR)?;
-        Status::mark(&Status::Open, STATUSDIR, &branch)?;
-
         let our_branch = &self
             .current_branch
             .as_ref()
             .ok_or(anyhow!("Not on a branch."))?;
-
-        // Write our negated items to the current branch
-        let negated_items = branching_items.as_negated();
-        negated_items.serialize_and_write(our_branch, LOCALDIR)?;
+        let branch = gen_pw();
+        let (move_out, move_in) = ledger::move_entries(
+            LOCALDIR,
+            our_branch,
+            &branch,
+            branching_items,
+            &self.operator,
+            &self.note,
+        )?;
+        let moved = [&move_out, &move_in];
+        ledger::mark_written(LOCALDIR, STATUSDIR, &moved, &[(Status::Open, &branch)])?;
+        if let Some(marketplace) = self.branch_marketplaces.get(*our_branch) {
+            Marketplace::mark(marketplace, MARKETDIR, &branch)?;
+        };
 
         // Return the NEW branch
         Ok(branch)
@@ -415,7 +1569,7 @@ impl Gui {
     /// You probably mean to use [`Self::new_branch_from_unselected`].
     fn get_unselected_items(&self) -> Vec<Entry> {
         let unsel = self.unselected.clone();
-        self.items
+        self.ledger
             .iter()
             .filter_map(|x| {
                 let fnsku = x.get_fnsku();
@@ -426,9 +1580,24 @@ impl Gui {
 
     /// Fill the Ui with a grid, displaying sums of the passed entries.
     fn show_current_branch_contents(&mut self, ui: &mut Ui) {
+        let summary = self.ledger.summary();
+        let filter = &mut self.condition_filter;
+        let describe = |x: &Option<Condition>| x.map(|c| c.as_str()).unwrap_or("Unknown");
+        let selected = filter.as_ref().map(describe).unwrap_or("All");
+        egui::ComboBox::from_label("Condition")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(filter, None, "All");
+                for condition in summary.conditions() {
+                    ui.selectable_value(filter, Some(*condition), describe(condition));
+                }
+            });
+
         Grid::new("item-grid").striped(true).show(ui, |ui| {
-            let mut sums = self.items.clone().get_as_sums();
-            sums.sort_by_key(|i| i.get_fnsku().to_string());
+            let sums = match self.condition_filter {
+                Some(condition) => summary.sums_of(condition),
+                None => summary.sums(),
+            };
             let un_set = &mut self.unselected;
 
             ui.label("");
@@ -439,14 +1608,16 @@ impl Gui {
             ui.label("Amz Size");
             ui.label("Condition");
             ui.label("Title");
+            ui.label("Notes");
             ui.end_row();
 
-            sums.into_iter()
+            sums.iter()
                 .filter(|entry| entry.get_units() > 0)
                 .for_each(|entry| {
                     let fnsku = entry.get_fnsku();
                     // When the fnsku is NOT in the map, display check.
-                    let mut check = !un_set.contains(fnsku);
+                    let checked = !un_set.contains(fnsku);
+                    let mut check = checked;
                     ui.checkbox(&mut check, "");
 
                     ui.label(entry.str_msku());
@@ -456,14 +1627,15 @@ impl Gui {
                     ui.label(entry.str_amz_size());
                     ui.label(entry.str_condition());
                     ui.label(entry.str_title());
+                    ui.label(self.notes.get(fnsku).map_or("", |x| x.as_str()));
                     ui.end_row();
 
-                    // When the fnsku is NOT checked, insert.
-                    // When check, remove.
-                    if !check {
-                        un_set.insert(fnsku.to_owned());
-                    } else {
-                        un_set.remove(fnsku);
+                    // When the fnsku is unchecked, insert.
+                    // When checked, remove.
+                    match (checked, check) {
+                        (true, false) => un_set.insert(fnsku.to_owned()),
+                        (false, true) => un_set.remove(fnsku),
+                        _ => false,
                     };
                 });
         });
@@ -476,11 +1648,11 @@ impl Gui {
         // This is probably terribly done, but it for sure works
         // Good enough for now
         let clone: Vec<Entry> = self
-            .items
-            .entries()
+            .ledger
             .as_group_by_case()
             .into_values()
             .flatten()
+            .cloned()
             .collect();
         let mut with_msku: Vec<_> = clone
             .into_iter()
@@ -498,7 +1670,7 @@ impl Gui {
             )
         });
         let loose_items_case_id: Vec<String> = self
-            .items
+            .ledger
             .multi_fnsku_cases()
             .into_iter()
             .map(|x| x.get_id().to_string())
@@ -511,7 +1683,7 @@ impl Gui {
             loose_items_case_id.contains(&case_id)
         });
 
-        packed.extend(loose.into_iter());
+        packed.extend(loose);
         let s = serde_json::to_string(&packed)?;
         Ok(std::fs::write(BOXCONTENTS, s)?)
     }
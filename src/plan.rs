@@ -1,29 +1,63 @@
+pub mod availability;
+pub mod condition;
+pub mod diff;
+pub mod hazmat;
+pub mod ledger;
+pub mod marketplace;
+pub mod portion;
+pub mod split;
 pub mod status;
+pub mod storage;
+pub mod summary;
+pub mod validate;
 
-use crate::{Brn, Fnsku, TreeJson};
+use crate::{
+    catalog::{Field, Source},
+    Branch, Brn, Fnsku, TreeJson,
+};
 use anyhow::Result;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     path::Path,
 };
 use uuid::Uuid;
 
+use self::{
+    condition::Condition,
+    diff::{EnrichmentMismatch, PlanDiff},
+    hazmat::Hazmat,
+    ledger::Ledger,
+    portion::Portion,
+    split::{SplitGroup, SplitRule},
+    summary::Summary,
+    validate::Violation,
+};
+
 pub trait Plan {
-    fn entries(&self) -> Vec<Entry>;
+    /// Returns every entry of [`Self`], in the order they were recorded.
+    ///
+    /// Every other method of [`Plan`] is built on this, without cloning.
+    fn iter(&self) -> impl Iterator<Item = &Entry>;
+
+    /// Returns a clone of every entry of [`Self`].
+    ///
+    /// You probably want to use [`Self::iter`], which does not allocate.
+    fn entries(&self) -> Vec<Entry> {
+        self.iter().cloned().collect()
+    }
 
-    /// Returns a clone of [`Self`] without the provided [`Fnsku`]s.
+    /// Returns the entries of [`Self`] without the provided [`Fnsku`]s.
     ///
     /// When [`Self`] does not contain any of the provided Fnsku, this is
-    /// just returning [`Self`] cloned and unchanged.
+    /// just returning every entry of [`Self`].
     ///
     /// [Fnksu]: (plaine::Fnsku)
-    fn remove_fnskus<I>(&self, i: I) -> Vec<Entry>
+    fn remove_fnskus<I>(&self, i: I) -> Vec<&Entry>
     where
         I: IntoIterator<Item = Fnsku>,
     {
         let pre = i.into_iter().collect::<HashSet<_>>();
-        self.entries()
-            .into_iter()
+        self.iter()
             // i is list of items to remove
             .filter(|x| !pre.contains(x.get_fnsku()))
             .collect()
@@ -65,15 +99,15 @@ pub trait Plan {
     /// See [Self::serialize_to_fs] for a version that writes to the local
     /// directory.
     fn serialize(&self) -> Result<TreeJson> {
-        let value = self.entries();
+        let value = self.iter().collect::<Vec<_>>();
         let json = serde_json::to_string_pretty(&value)?;
         Ok(json)
     }
 
     fn as_negated(&self) -> Vec<Entry> {
-        self.entries()
-            .into_iter()
-            .map(|mut x| {
+        self.iter()
+            .map(|x| {
+                let mut x = x.clone();
                 let old_units = x.get_units();
                 let new_units = old_units.checked_neg().unwrap_or_default();
                 x.set_units(new_units);
@@ -84,109 +118,95 @@ pub trait Plan {
 
     /// Return the number of cases in [`Self`] with more than 0 units.
     fn number_of_real_cases(&self) -> usize {
-        self.as_folded_cases()
+        self.units_of_cases()
             .into_values()
-            .map(|v| v.into_iter().map(|x: Entry| x.units).sum::<i32>())
             .filter(|x| x.is_positive())
             .count()
     }
 
     /// Return the number of cases in [`Self`] with amounts not equal to 0.
     fn number_of_nonzero_cases(&self) -> usize {
-        self.as_folded_cases()
+        self.units_of_cases()
             .into_values()
-            .map(|v| v.into_iter().map(|x: Entry| x.units).sum())
             .filter(|x: &i32| x != &0)
             .count()
     }
 
     /// Return the number of cases in [`Self`] with amounts that are below 0.
     fn negative_unit_case_count(&self) -> usize {
-        self.as_folded_cases()
+        self.units_of_cases()
             .into_values()
-            .map(|v| v.into_iter().map(|x: Entry| x.units).sum())
-            .filter(|x: &i32| x != &0)
+            .filter(|x: &i32| x < &0)
             .count()
     }
 
+    /// Return a HashMap containing case id as a key, and units as value.
+    fn units_of_cases(&self) -> HashMap<&str, i32> {
+        let mut cases = HashMap::new();
+        for entry in self.iter() {
+            *cases.entry(entry.get_id()).or_default() += entry.get_units();
+        }
+        cases
+    }
+
     /// Returns a copy of [`Self`], mapped by case id, and summed by equal fnsku.
-    fn as_folded_cases(&self) -> HashMap<String, Vec<Entry>> {
-        let grouped = self.as_group_by_case();
-        grouped.iter().fold(HashMap::new(), |mut acc, (k, v)| {
-            let vv = v.to_owned().get_as_sums();
-            acc.insert(k.to_string(), vv);
-            acc
-        })
-    }
-
-    /// Returns a copy of [`Self`] as raw entries, mapped by case id.
-    fn as_group_by_case(&self) -> HashMap<String, Vec<Entry>> {
-        let hm = HashMap::new();
-        self.entries()
-            .iter()
-            .fold(hm, |mut acc, entry| match acc.get_mut(entry.get_id()) {
-                Some(case) => {
-                    case.push(entry.clone());
-                    acc
-                }
-                None => {
-                    let id = entry.get_id().to_string();
-                    acc.insert(id, vec![entry.clone()]);
-                    acc
-                }
-            })
+    fn as_folded_cases(&self) -> HashMap<&str, Vec<Entry>> {
+        self.as_group_by_case()
+            .into_iter()
+            .map(|(id, case)| (id, case.get_as_sums()))
+            .collect()
     }
 
+    /// Returns the entries of [`Self`], mapped by case id.
+    fn as_group_by_case(&self) -> HashMap<&str, Vec<&Entry>> {
+        let mut cases: HashMap<&str, Vec<&Entry>> = HashMap::new();
+        for entry in self.iter() {
+            cases.entry(entry.get_id()).or_default().push(entry);
+        }
+        cases
+    }
 
+    /// Returns the Fnskus held by each case id, ignoring their units.
+    fn fnskus_of_cases(&self) -> HashMap<&str, HashSet<&str>> {
+        let mut cases: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for entry in self.iter() {
+            let fnskus = cases.entry(entry.get_id()).or_default();
+            fnskus.insert(entry.get_fnsku());
+        }
+        cases
+    }
 
     /// This function returns the entries that "Loose".
     ///
-    /// This is determined by folding all entries by their case id and filtering
-    /// out any case ids that do not contain more than 1 fnsku.
+    /// This is determined by grouping all entries by their case id and
+    /// filtering out any case ids that do not contain more than 1 fnsku.
     ///
     /// The inverse of this function is [`Self::single_fnsku_cases`].
-    fn multi_fnsku_cases(&self) -> Vec<Entry> {
-        let case_ids_with_one_fnsku = self
-            .as_folded_cases()
-            .into_iter()
-            .filter_map(|(caseid, entries)| entries.get_as_sums().len().gt(&1).then_some(caseid))
-            .collect::<Vec<_>>();
-        self.entries()
-            .into_iter()
-            .filter(|x| {
-                let case_id = x.get_id().to_string();
-                case_ids_with_one_fnsku.contains(&case_id)
-            })
+    fn multi_fnsku_cases(&self) -> Vec<&Entry> {
+        let cases = self.fnskus_of_cases();
+        self.iter()
+            .filter(|x| cases.get(x.get_id()).is_some_and(|x| x.len() > 1))
             .collect()
     }
 
     /// This function returns the entries that "Packed".
     ///
-    /// This is determined by folding all entries by their case id and filtering
-    /// out any case ids that do not contain a single fnsku.
+    /// This is determined by grouping all entries by their case id and
+    /// filtering out any case ids that do not contain a single fnsku.
     ///
-    /// The inverse of this function is [`Self::single_fnsku_cases`].
-    fn single_fnsku_cases(&self) -> Vec<Entry> {
-        let case_ids_with_one_fnsku = self
-            .as_folded_cases()
-            .into_iter()
-            .filter_map(|(caseid, entries)| entries.get_as_sums().len().eq(&1).then_some(caseid))
-            .collect::<Vec<_>>();
-        self.entries()
-            .into_iter()
-            .filter(|x| {
-                let case_id = x.get_id().to_string();
-                case_ids_with_one_fnsku.contains(&case_id)
-            })
+    /// The inverse of this function is [`Self::multi_fnsku_cases`].
+    fn single_fnsku_cases(&self) -> Vec<&Entry> {
+        let cases = self.fnskus_of_cases();
+        self.iter()
+            .filter(|x| cases.get(x.get_id()).is_some_and(|x| x.len() == 1))
             .collect()
     }
 
     /// A convenience function for determining if the [`Self`] is single.
     ///
-    /// This will call [`Self::multi_fnsku_cases`] and will return true
-    /// if the result of that function is an empty vector.
+    /// Returns true if [`Self::multi_fnsku_cases`] would be empty.
     fn is_single(&self) -> bool {
-        self.multi_fnsku_cases().is_empty()
+        self.fnskus_of_cases().values().all(|x| x.len() <= 1)
     }
 
     /// Return the total cases that [`Self`] has seen.
@@ -198,7 +218,7 @@ pub trait Plan {
     /// This function is mostly used for internal records. You probably
     /// want to use [`Self::number_of_real_cases`].
     fn number_of_seen_cases(&self) -> usize {
-        self.as_group_by_case().keys().count()
+        self.iter().map(|x| x.get_id()).collect::<HashSet<_>>().len()
     }
 
     /// Sum all Entries of [`Self`] into like Fnskus.
@@ -211,61 +231,173 @@ pub trait Plan {
     /// This function differs from the others within [`Self`] in that it
     /// immediately breaks the first bound.
     fn get_as_sums(&self) -> Vec<Entry> {
-        let fold = |mut acc: HashMap<String, Entry>, x: Entry| {
-            if let Some(inner_entry) = acc.get_mut(x.get_fnsku()) {
-                inner_entry.units += x.get_units();
-                acc
-            } else {
-                acc.insert(x.fnsku.to_owned(), x.clone());
-                acc
-            }
-        };
-        let hashmap = HashMap::<String, Entry>::new();
-        let iter = self.entries().into_iter();
-        // Fold each eq Fnsku into itself.
-        // No need to keep the keys, Entry has a sku field.
-        iter.fold(hashmap, fold).into_values().collect()
+        // Only the first entry of each Fnsku is cloned, the rest are added to it.
+        let mut sums: HashMap<&str, Entry> = HashMap::new();
+        for entry in self.iter() {
+            match sums.get_mut(entry.get_fnsku()) {
+                Some(sum) => sum.units += entry.get_units(),
+                None => {
+                    sums.insert(entry.get_fnsku(), entry.clone());
+                }
+            };
+        }
+        sums.into_values().collect()
     }
 
     /// Return a HashMap containing fnsku as a key, and units as value.
-    fn units_of_skus(&self) -> HashMap<String, i32> {
-        let fold = |mut acc: HashMap<String, i32>, entry: Entry| {
-            // Skus that have already been seen can be added to.
-            if let Some(units) = acc.get_mut(entry.get_fnsku()) {
-                *units += entry.get_units();
-                acc
-            } else {
-                // New skus can be inserted
-                let id = entry.get_fnsku().to_string();
-                acc.insert(id, entry.units);
-                acc
-            }
-        };
-
-        // Fold each entry with equal skus into each other.
-        self.entries().into_iter().fold(HashMap::new(), fold)
+    fn units_of_skus(&self) -> HashMap<&str, i32> {
+        let mut skus = HashMap::new();
+        for entry in self.iter() {
+            *skus.entry(entry.get_fnsku()).or_default() += entry.get_units();
+        }
+        skus
     }
 
     /// Returns the sum of all units in [`Self`].
     fn units(&self) -> i32 {
-        self.entries()
-            .get_as_sums()
-            .into_iter()
-            .map(|x| x.get_units())
-            .sum()
+        self.iter().map(|x| x.get_units()).sum()
     }
 
-    fn get_case_named(&self, case_name: &str) -> Vec<Entry> {
-        self.entries()
-            .into_iter()
-            .filter(|x| x.get_id().eq(case_name))
-            .collect::<Vec<_>>()
+    /// Returns the entries of [`Self`] that are in the given `condition`.
+    ///
+    /// A `condition` of `None` returns the entries without a known condition.
+    fn with_condition(&self, condition: Option<Condition>) -> Vec<&Entry> {
+        self.iter()
+            .filter(|x| x.get_condition() == &condition)
+            .collect()
+    }
+
+    /// Returns each distinct condition within [`Self`].
+    fn conditions(&self) -> BTreeSet<Option<Condition>> {
+        self.iter().map(|x| *x.get_condition()).collect()
+    }
+
+    /// Returns the entries of [`Self`] that are, or are not, hazmat.
+    ///
+    /// Entries without a known classification are taken to not be hazmat.
+    fn hazmat_entries(&self, hazmat: bool) -> Vec<&Entry> {
+        self.iter().filter(|x| x.is_hazmat() == hazmat).collect()
+    }
+
+    /// Returns whether each Fnsku that still holds units is hazmat.
+    ///
+    /// An Fnsku is hazmat if any of its entries is, as negated entries may
+    /// have been written without a classification.
+    fn hazmat_fnskus(&self) -> BTreeMap<&str, bool> {
+        let units = self.units_of_skus();
+        let mut fnskus: BTreeMap<&str, bool> = BTreeMap::new();
+        for entry in self.iter() {
+            let fnsku = entry.get_fnsku();
+            if units.get(fnsku).is_some_and(|x| x > &0) {
+                *fnskus.entry(fnsku).or_default() |= entry.is_hazmat();
+            };
+        }
+        fnskus
+    }
+
+    /// Returns true if [`Self`] holds both hazmat and non-hazmat units.
+    ///
+    /// Amazon routes hazmat to separate shipments, so these cannot share a
+    /// branch. See [`Plan::hazmat_fnskus`].
+    fn mixes_hazmat(&self) -> bool {
+        let hazmat = self.hazmat_fnskus().into_values().collect::<HashSet<_>>();
+        hazmat.len() > 1
+    }
+
+    /// Returns the entries of each case that still hold units.
+    ///
+    /// Each case is summed by Fnsku, see [`Self::as_folded_cases`].
+    fn remaining(&self) -> Vec<Entry> {
+        self.as_folded_cases()
+            .into_values()
+            .flatten()
+            .filter(|x| x.get_units() != 0)
+            .collect()
+    }
+
+    /// Summarize [`Self`] once, to be shown many times, see [`Summary`].
+    fn summarize(&self) -> Summary {
+        Summary::new(self)
+    }
+
+    /// Returns the entries of `fnsku` that make up `portion`, see [`Portion::take`].
+    ///
+    /// # Errors
+    ///
+    /// `portion` cannot be taken from the remaining entries of [`Self`].
+    fn portion(&self, fnsku: &str, portion: &Portion) -> Result<Vec<Entry>> {
+        portion.take(self, fnsku)
+    }
+
+    /// Returns the remaining entries of the cases `ids`, see [`portion::take_cases`].
+    ///
+    /// # Errors
+    ///
+    /// No case is given, or a case no longer holds any units.
+    fn take_cases(&self, ids: &BTreeSet<String>) -> Result<Vec<Entry>> {
+        portion::take_cases(self, ids)
+    }
+
+    /// Returns every record of [`Self`] that breaks the ledger, see [`Violation::find`].
+    fn validate(&self) -> Vec<Violation> {
+        Violation::find(self)
+    }
+
+    /// Divide the remaining entries of [`Self`] by `rule`, see [`SplitRule`].
+    fn split_by(&self, rule: SplitRule) -> Vec<SplitGroup> {
+        rule.split(self)
+    }
+
+    /// Compare [`Self`] with a later state of it, see [`PlanDiff::new`].
+    fn diff<P: Plan>(&self, after: &P) -> PlanDiff {
+        PlanDiff::new(self, after)
+    }
+
+    /// Returns the enriched fields that [`Self`] and `other` disagree on.
+    ///
+    /// See [`EnrichmentMismatch::between`].
+    fn enrichment_mismatches<P: Plan>(&self, other: &P) -> Vec<EnrichmentMismatch> {
+        EnrichmentMismatch::between(self, other)
+    }
+
+    fn get_case_named(&self, case_name: &str) -> Vec<&Entry> {
+        self.iter().filter(|x| x.get_id().eq(case_name)).collect()
+    }
+}
+
+/// Read the entries of every branch within `dir`, see [`Ledger::load_all`].
+///
+/// # Errors
+///
+/// The underlying file system fails at reading `dir`.
+pub fn load_branches<P>(dir: P) -> Result<HashMap<Branch, Vec<Entry>>>
+where
+    P: AsRef<Path>,
+{
+    let ledgers = Ledger::load_all(dir)?;
+    let branches = ledgers
+        .into_iter()
+        .map(|(branch, ledger)| (branch, ledger.entries()))
+        .collect();
+    Ok(branches)
+}
+
+impl Plan for [Entry] {
+    fn iter(&self) -> impl Iterator<Item = &Entry> {
+        <[Entry]>::iter(self)
     }
 }
 
 impl Plan for Vec<Entry> {
-    fn entries(&self) -> Vec<Entry> {
-        self.clone()
+    fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.as_slice().iter()
+    }
+}
+
+/// Entries borrowed from another [`Plan`], e.g. by [`Plan::with_condition`].
+impl Plan for [&Entry] {
+    fn iter(&self) -> impl Iterator<Item = &Entry> {
+        <[&Entry]>::iter(self).copied()
     }
 }
 
@@ -281,13 +413,22 @@ pub struct Entry {
     msku: Option<String>,
     title: Option<String>,
     asin: Option<String>,
-    condition: Option<String>,
+    #[serde(default, deserialize_with = "condition::deserialize_lenient")]
+    condition: Option<Condition>,
     units: i32,
     total_pounds: Option<f32>,
     id: String,
     upc: Option<String>,
     case_dimensions: Option<[f32; 3]>,
     amz_dimensions: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hazmat: Option<Hazmat>,
+    /// Imported as loose units, rather than as a packed case.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    loose: bool,
+    /// The report each enriched field was read from.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sources: BTreeMap<Field, Source>,
 }
 impl Entry {
     pub fn get_amz_dimensions(&self) -> Option<[f32; 3]> {
@@ -317,10 +458,14 @@ impl Entry {
         self.asin = set;
     }
 
-    pub fn set_condition(&mut self, set: Option<String>) {
+    pub fn set_condition(&mut self, set: Option<Condition>) {
         self.condition = set;
     }
 
+    pub fn set_hazmat(&mut self, set: Option<Hazmat>) {
+        self.hazmat = set;
+    }
+
     pub fn set_units(&mut self, set: i32) {
         self.units = set;
     }
@@ -333,6 +478,10 @@ impl Entry {
         self.id = set;
     }
 
+    pub fn set_loose(&mut self, set: bool) {
+        self.loose = set;
+    }
+
     pub fn set_upc(&mut self, set: Option<String>) {
         self.upc = set;
     }
@@ -357,10 +506,19 @@ impl Entry {
         &self.asin
     }
 
-    pub fn get_condition(&self) -> &Option<String> {
+    pub fn get_condition(&self) -> &Option<Condition> {
         &self.condition
     }
 
+    pub fn get_hazmat(&self) -> &Option<Hazmat> {
+        &self.hazmat
+    }
+
+    /// Returns true if [`Self`] is a known hazmat, see [`Hazmat::is_hazmat`].
+    pub fn is_hazmat(&self) -> bool {
+        self.hazmat.is_some_and(|x| x.is_hazmat())
+    }
+
     pub fn get_units(&self) -> i32 {
         self.units
     }
@@ -373,10 +531,47 @@ impl Entry {
         &self.id
     }
 
+    pub fn is_loose(&self) -> bool {
+        self.loose
+    }
+
     pub fn get_upc(&self) -> &Option<String> {
         &self.upc
     }
 
+    /// Returns where the value of an enriched `field` was read from.
+    pub fn get_source(&self, field: Field) -> Option<&Source> {
+        self.sources.get(&field)
+    }
+
+    pub fn set_source(&mut self, field: Field, set: Option<Source>) {
+        match set {
+            Some(source) => self.sources.insert(field, source),
+            None => self.sources.remove(&field),
+        };
+    }
+
+    /// Returns the value of every field that was read from a report.
+    pub fn enrichment(&self) -> BTreeMap<Field, String> {
+        let dims = |x: [f32; 3]| format!("{} x {} x {}", x[0], x[1], x[2]);
+        let describe = |field: &Field| match field {
+            Field::Msku => self.msku.clone(),
+            Field::Asin => self.asin.clone(),
+            Field::Title => self.title.clone(),
+            Field::Upc => self.upc.clone(),
+            Field::AmzSize => self.amz_size.clone(),
+            Field::Condition => self.condition.map(|x| x.to_string()),
+            Field::TotalPounds => self.total_pounds.map(|x| x.to_string()),
+            Field::AmzDimensions => self.amz_dimensions.map(dims),
+            Field::Hazmat => self.hazmat.map(|x| x.to_string()),
+            Field::Available | Field::UnitVolume => None,
+        };
+        self.sources
+            .keys()
+            .filter_map(|field| Some((*field, describe(field)?)))
+            .collect()
+    }
+
     pub fn set_dimensions(&mut self, dims: Option<[f32; 3]>) {
         let Some(udims) = dims else {
             self.case_dimensions = None;
@@ -453,7 +648,7 @@ impl Entry {
 
     pub fn str_condition(&self) -> &str {
         match &self.condition {
-            Some(x) => x,
+            Some(x) => x.as_str(),
             None => "",
         }
     }
@@ -474,6 +669,14 @@ impl Entry {
 mod tests {
     use super::*;
 
+    fn entry(fnsku: &str, id: &str, units: i32) -> Entry {
+        let mut entry = Entry::default();
+        entry.set_fnsku(fnsku.to_string());
+        entry.set_id(id.to_string());
+        entry.set_units(units);
+        entry
+    }
+
     #[test]
     fn plan_group_by_case() {
         let mut entry1 = Entry::default();
@@ -557,6 +760,591 @@ mod tests {
         let plan = vec![entry1, entry2];
         let _neg = plan.as_negated();
     }
+
+    #[test]
+    fn plan_filter_by_condition() -> Result<()> {
+        let mut entry1 = Entry::default();
+        entry1.set_fnsku(String::from("zzz"));
+        entry1.set_condition(Some("NewItem".parse()?));
+
+        let mut entry2 = entry1.clone();
+        entry2.set_condition(Some("4".parse()?));
+
+        let plan = vec![entry1, entry2.clone(), Entry::default()];
+        assert_eq!(plan.conditions().len(), 3);
+
+        let used = plan.with_condition(Some(Condition::UsedAcceptable));
+        assert_eq!(used.len(), 1);
+        assert_eq!(used[0].str_condition(), entry2.str_condition());
+        Ok(())
+    }
+
+    #[test]
+    fn plan_summary() {
+        let plan = vec![
+            entry("bbb", "Box-2", 6),
+            entry("aaa", "Box-1", 4),
+            entry("aaa", "Box-1", -4),
+            entry("aaa", "Box-2", 3),
+        ];
+
+        let summary = plan.summarize();
+        let fnskus = summary.sums().iter().map(|x| x.get_fnsku()).collect::<Vec<_>>();
+        assert_eq!(fnskus, ["aaa", "bbb"]);
+        assert_eq!(summary.units_of("aaa"), 3);
+        assert_eq!(summary.units_of("ccc"), 0);
+        assert_eq!(summary.units(), 9);
+
+        // Box-1 is empty, so only Box-2 remains.
+        let cases = summary.cases();
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].0, "Box-2");
+        assert_eq!(cases[0].1.units(), 9);
+
+        // An Fnsku of two conditions is summed separately for each.
+        let mut plan = plan;
+        plan[3].set_condition(Some(Condition::New));
+        let mut used = entry("aaa", "Box-3", 2);
+        used.set_condition(Some(Condition::UsedGood));
+        plan.push(used);
+        let summary = plan.summarize();
+        let units = |condition| {
+            let sums = summary.sums_of(condition);
+            sums.iter().map(|x| (x.get_fnsku(), x.get_units())).collect::<Vec<_>>()
+        };
+        assert_eq!(units(Some(Condition::New)), [("aaa", 3)]);
+        assert_eq!(units(Some(Condition::UsedGood)), [("aaa", 2)]);
+        assert_eq!(units(None), [("aaa", 0), ("bbb", 6)]);
+        assert!(units(Some(Condition::Refurbished)).is_empty());
+    }
+
+    #[test]
+    fn availability_over_allocation() {
+        use crate::{
+            catalog::{Catalog, Product},
+            read::ReportKind,
+        };
+        use availability::AvailabilityReport;
+
+        let source = Source::new(ReportKind::AmzFbaInventory, "afi.txt", 2);
+        let product = |fnsku: &str, available: u32| Product {
+            fnsku: Some(fnsku.to_string()),
+            available: source.tag(Some(available)),
+            ..Default::default()
+        };
+        let catalog = Catalog::from_products([product("aaa", 10), product("bbb", 10)]);
+
+        let branches = HashMap::from([
+            (String::from("one"), vec![entry("aaa", "case-1", 6), entry("bbb", "case-2", 4)]),
+            (String::from("two"), vec![entry("aaa", "case-3", 6), entry("ccc", "case-4", 1)]),
+        ]);
+        let report = AvailabilityReport::new(&branches, &catalog);
+        assert_eq!(report.allocations.len(), 3);
+
+        let over = report.over_allocated();
+        let fnskus = over.iter().map(|x| x.fnsku.as_str()).collect::<Vec<_>>();
+        assert_eq!(fnskus, ["aaa", "ccc"]);
+        assert_eq!(over[0].planned(), 12);
+        assert_eq!(over[0].shortfall(), 2);
+        assert_eq!(report.over_allocated_in("one").len(), 1);
+    }
+
+    #[test]
+    fn storage_estimate_by_rate() -> Result<()> {
+        use crate::{
+            catalog::{Catalog, Product},
+            read::{ReportKind, ReportMonth},
+        };
+        use storage::{StorageEstimate, StorageRates};
+
+        let source = Source::new(ReportKind::MonthlyStorageFees, "msf.csv", 2);
+        let catalog = Catalog::from_products([
+            Product {
+                fnsku: Some(String::from("aaa")),
+                amz_size: source.tag(Some(String::from("Standard-Size"))),
+                volume: source.tag(Some(0.5)),
+                ..Default::default()
+            },
+            // A size tier without a configured rate, charged in a peak month.
+            Product {
+                fnsku: Some(String::from("ccc")),
+                amz_size: source.tag(Some(String::from("Small and Light"))),
+                volume: source.tag(Some(0.2)),
+                dated: Some(ReportMonth::new(2024, 11)?),
+                storage_rate: Some(3.0),
+                unit_storage_fee: Some(0.5),
+                ..Default::default()
+            },
+        ]);
+
+        let mut entry = Entry::default();
+        entry.set_fnsku(String::from("aaa"));
+        entry.set_units(10);
+        let mut unknown = entry.clone();
+        unknown.set_fnsku(String::from("bbb"));
+        let mut reported = entry.clone();
+        reported.set_fnsku(String::from("ccc"));
+
+        let rates = StorageRates::default();
+        let plan = vec![entry.clone(), unknown, reported];
+        let estimate = StorageEstimate::new(&plan, &catalog, &rates, None)?;
+        assert_eq!(estimate.currency, "USD");
+        assert_eq!(estimate.costs.len(), 3);
+        assert_eq!(estimate.unknown().len(), 1);
+        assert!((estimate.total_off_peak() - 3.9).abs() < 0.001);
+        assert!((estimate.total_peak() - 18.0).abs() < 0.001);
+        assert!((estimate.total_in(11) - 18.0).abs() < 0.001);
+        assert!((estimate.total_in(3) - 3.9).abs() < 0.001);
+        assert!((estimate.total_charged() - 5.0).abs() < 0.001);
+        let ccc = &estimate.costs[2];
+        assert_eq!((ccc.off_peak, ccc.charged), (None, Some(5.0)));
+
+        let ca = Some(marketplace::Marketplace::CA);
+        assert!(StorageEstimate::new(&vec![], &catalog, &rates, ca).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ledger_moves() -> Result<()> {
+        use ledger::{move_entries, Ledger, TransactionKind};
+
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir)?;
+        let mut entry = Entry::default();
+        entry.set_fnsku(String::from("aaa"));
+        entry.set_units(10);
+        let mut other = entry.clone();
+        other.set_fnsku(String::from("bbb"));
+        // Branches written before the ledger are still read.
+        let legacy = dir.join("source_legacy.json");
+        std::fs::write(&legacy, serde_json::to_string(&vec![entry.clone(), other.clone()])?)?;
+        let mut negated = entry;
+        negated.set_units(-3);
+        let legacy_out = dir.join("source_legacy-out.json");
+        std::fs::write(&legacy_out, serde_json::to_string(&vec![negated])?)?;
+        // A copy of the dir rewrites mtimes, legacy files still come first.
+        let now = std::time::SystemTime::now();
+        let minute = std::time::Duration::from_secs(60);
+        std::fs::File::options().write(true).open(&legacy)?.set_modified(now + minute)?;
+        std::fs::File::options().write(true).open(&legacy_out)?.set_modified(now - minute)?;
+
+        let moved = move_entries(&dir, "source", "dest", vec![other], "pat", "Ship now");
+        let ledgers = moved.and_then(|_| Ledger::load_all(&dir));
+        std::fs::remove_dir_all(&dir)?;
+        let ledgers = ledgers?;
+
+        let source = &ledgers["source"];
+        let kinds = source.transactions().iter().map(|x| x.kind).collect::<Vec<_>>();
+        let expected = [
+            TransactionKind::Import,
+            TransactionKind::MoveOut,
+            TransactionKind::MoveOut,
+        ];
+        assert_eq!(kinds, expected);
+        assert!(source.transactions()[1].legacy);
+        assert_eq!(source.units(), 7);
+        assert_eq!(source.units_of_skus()["bbb"], 0);
+
+        let dest = &ledgers["dest"];
+        let move_in = &dest.transactions()[0];
+        assert_eq!(move_in.kind, TransactionKind::MoveIn);
+        assert_eq!((move_in.operator.as_str(), move_in.note.as_str()), ("pat", "Ship now"));
+        let link = move_in.link.as_ref().expect("moves are linked");
+        assert!(source.get(&link.transaction).is_some());
+        assert_eq!(dest.units(), 10);
+
+        // The source as it was before the move.
+        let import = &source.transactions()[0];
+        assert_eq!(source.at(&import.id).map(|x| x.units()), Some(20));
+        assert_eq!(source.upto(0).len(), 0);
+        let changes = source.changes_of(&link.transaction).expect("move out is recorded");
+        assert_eq!(changes.fnskus["bbb"].delta(), -10);
+        assert!(!changes.fnskus.contains_key("aaa"));
+        Ok(())
+    }
+
+    #[test]
+    fn ledger_reverts() -> Result<()> {
+        use ledger::{move_entries, revert_move, Ledger};
+        use status::Status;
+
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let status_dir = dir.join("status");
+        std::fs::create_dir_all(&status_dir)?;
+        let mut entry = Entry::default();
+        entry.set_fnsku(String::from("aaa"));
+        entry.set_units(10);
+        let run = || -> Result<_> {
+            let (first, _) = move_entries(&dir, "source", "dest", vec![entry.clone()], "", "")?;
+            let (_, second) = move_entries(&dir, "source", "checked", vec![entry], "", "")?;
+            Status::mark(&Status::Check, &status_dir, "checked")?;
+            let refused = revert_move(&dir, &status_dir, "checked", &second.id, "", "");
+            let reverted = revert_move(&dir, &status_dir, "source", &first.id, "pat", "");
+            let move_in = first.link.expect("moves are linked").transaction;
+            let again = revert_move(&dir, &status_dir, "dest", &move_in, "", "");
+            let ledgers = Ledger::load_all(&dir)?;
+            let statuses = Status::load_all(&status_dir)?;
+            Ok((refused, reverted, again, ledgers, statuses))
+        };
+        let ran = run();
+        std::fs::remove_dir_all(&dir)?;
+        let (refused, reverted, again, ledgers, statuses) = ran?;
+
+        assert!(refused.is_err());
+        let (returned, removed) = reverted?;
+        assert_eq!((returned.units(), removed.units()), (10, -10));
+        assert!(again.is_err());
+        assert_eq!(ledgers["source"].units(), -10);
+        assert_eq!(ledgers["dest"].units(), 0);
+        assert_eq!(statuses["dest"], Status::Closed);
+        assert_eq!(statuses["checked"], Status::Check);
+        Ok(())
+    }
+
+    #[test]
+    fn ledger_writes_all_or_none() -> Result<()> {
+        use ledger::{mark_written, write_all, Ledger, Transaction, TransactionKind};
+        use status::Status;
+
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let status_dir = dir.join("status");
+        std::fs::create_dir_all(&status_dir)?;
+        let mut entry = Entry::default();
+        entry.set_fnsku(String::from("aaa"));
+        entry.set_units(10);
+        let first = Transaction::new("first", TransactionKind::Import, vec![entry.clone()]);
+        let second = Transaction::new("second", TransactionKind::Import, vec![entry]);
+        let run = || -> Result<_> {
+            // A directory in the way of the second file fails its rename.
+            let blocked = dir.join(format!("second_{}.json", second.id));
+            std::fs::create_dir_all(blocked.join("taken"))?;
+            let renamed = write_all(&dir, &[&first, &second]);
+            std::fs::remove_dir_all(&blocked)?;
+            let files = || Ok::<_, std::io::Error>(std::fs::read_dir(&dir)?.count());
+            let after_rename = files()?;
+
+            write_all(&dir, &[&first])?;
+            let marks = [(Status::Closed, "first"), (Status::Open, "no/such")];
+            let marked = mark_written(&dir, &status_dir, &[&first], &marks);
+            let after_mark = files()?;
+            Ok((renamed, marked, [after_rename, after_mark], Ledger::load_all(&dir)?))
+        };
+        let ran = run();
+        let statuses = Status::load_all(&status_dir);
+        std::fs::remove_dir_all(&dir)?;
+        let (renamed, marked, files, ledgers) = ran?;
+
+        assert!(renamed.is_err());
+        assert!(marked.is_err());
+        // Only the status directory is left, without a mark of `first`.
+        assert_eq!(files, [1, 1]);
+        assert!(ledgers.is_empty());
+        assert!(statuses?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ledger_merges() -> Result<()> {
+        use crate::{catalog::Source, read::ReportKind};
+        use ledger::{merge_branches, revert_move, write_all, Ledger, Transaction, TransactionKind};
+        use status::Status;
+
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let status_dir = dir.join("status");
+        std::fs::create_dir_all(&status_dir)?;
+        let source = Source::new(ReportKind::AmzFbaInventory, "inventory.csv", 2);
+        let listed = |fnsku: &str, id: &str, msku: &str| {
+            let mut entry = entry(fnsku, id, 6);
+            entry.set_msku(Some(msku.to_string()));
+            entry.set_source(Field::Msku, Some(source.clone()));
+            entry
+        };
+        let small = vec![listed("aaa", "case-1", "old"), listed("bbb", "case-2", "bbb")];
+        let large = vec![listed("aaa", "case-3", "new")];
+        let run = || -> Result<_> {
+            let shipped = vec![listed("bbb", "case-2", "bbb")].as_negated();
+            write_all(
+                &dir,
+                &[
+                    &Transaction::new("small", TransactionKind::Import, small),
+                    &Transaction::new("large", TransactionKind::Import, large),
+                    &Transaction::new("small", TransactionKind::Adjustment, shipped),
+                ],
+            )?;
+            let merged = merge_branches(&dir, &status_dir, "small", "large", "pat", "")?;
+            let again = merge_branches(&dir, &status_dir, "small", "large", "pat", "");
+            let move_in = &merged.move_in.id;
+            let reverted = revert_move(&dir, &status_dir, "large", move_in, "pat", "");
+            let ledgers = Ledger::load_all(&dir)?;
+            let statuses = Status::load_all(&status_dir)?;
+            Ok((merged, again, reverted, ledgers, statuses))
+        };
+        let ran = run();
+        std::fs::remove_dir_all(&dir)?;
+        let (merged, again, reverted, ledgers, statuses) = ran?;
+
+        // Only what remains on the source is moved.
+        assert_eq!(merged.move_in.entries.len(), 1);
+        assert_eq!(ledgers["small"].units(), 0);
+        assert_eq!(ledgers["large"].units_of_skus()["aaa"], 12);
+        assert_eq!(statuses["small"], Status::Closed);
+        assert!(again.is_err());
+        // The closed source cannot take the units back, so none are lost.
+        let err = reverted.expect_err("small is closed");
+        assert!(err.to_string().contains("small is marked"));
+        assert_eq!(ledgers["large"].units(), 12);
+
+        let mismatch = &merged.mismatches[0];
+        assert_eq!(merged.mismatches.len(), 1);
+        assert_eq!((mismatch.fnsku.as_str(), mismatch.field), ("aaa", Field::Msku));
+        assert_eq!((mismatch.ours.as_str(), mismatch.theirs.as_str()), ("new", "old"));
+        Ok(())
+    }
+
+    #[test]
+    fn split_by_rule() -> Result<()> {
+        use ledger::{split_branch, write_all, Ledger, Transaction, TransactionKind};
+        use split::SplitRule;
+        use status::Status;
+
+        let sized = |fnsku: &str, id: &str, size: &str, pounds: f32| {
+            let mut entry = entry(fnsku, id, 4);
+            entry.set_amz_size(Some(size.to_string()));
+            entry.set_total_pounds(Some(pounds));
+            entry
+        };
+        let plan = vec![
+            sized("aaa", "case-1", "Large Standard-Size", 20.0),
+            sized("bbb", "case-2", "Small Oversize", 60.0),
+            sized("aaa", "case-3", "Large Standard-Size", 10.0),
+            sized("ccc", "case-3", "Large Standard-Size", 10.0),
+        ];
+        let labels = |rule| {
+            let groups = plan.split_by(rule);
+            groups.into_iter().map(|x| (x.label, x.entries.len())).collect::<Vec<_>>()
+        };
+        let tiers = labels(SplitRule::SizeTier);
+        assert_eq!(tiers, vec![("Oversize".into(), 1), ("Standard".into(), 3)]);
+        let weights = labels(SplitRule::Weight { pounds: 50.0 });
+        assert_eq!(weights, vec![("At least 50 lb".into(), 1), ("Under 50 lb".into(), 3)]);
+        let packs = labels(SplitRule::PackType);
+        assert_eq!(packs, vec![("Mixed Fnskus".into(), 2), ("Single Fnsku".into(), 2)]);
+        assert_eq!(plan.split_by(SplitRule::Condition).len(), 1);
+
+        // A case is never divided, even when its Fnskus disagree.
+        let mut mixed = vec![
+            sized("aaa", "case-4", "Large Standard-Size", 5.0),
+            sized("bbb", "case-4", "Small Oversize", 5.0),
+            sized("ccc", "case-5", "Large Standard-Size", 5.0),
+        ];
+        mixed[0].set_condition(Some(Condition::ALL[0]));
+        mixed[1].set_condition(Some(Condition::ALL[1]));
+        let labels = |rule| {
+            let groups = mixed.split_by(rule);
+            groups.into_iter().map(|x| (x.label, x.entries.len())).collect::<Vec<_>>()
+        };
+        let tiers = labels(SplitRule::SizeTier);
+        assert_eq!(tiers, vec![("Mixed size tiers".into(), 2), ("Standard".into(), 1)]);
+        let conditions = labels(SplitRule::Condition);
+        let expected = vec![("Mixed conditions".into(), 2), ("Unknown condition".into(), 1)];
+        assert_eq!(conditions, expected);
+
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let status_dir = dir.join("status");
+        std::fs::create_dir_all(&status_dir)?;
+        let run = || -> Result<_> {
+            let import = Transaction::new("trunk", TransactionKind::Import, plan.clone());
+            write_all(&dir, &[&import])?;
+            let named = |groups: Vec<SplitGroup>| {
+                let named = groups.into_iter().enumerate();
+                named.map(|(idx, x)| (idx.to_string(), x)).collect::<Vec<_>>()
+            };
+            // Groups that leave units behind, or that take more than remains.
+            let mut short = plan.split_by(SplitRule::PackType);
+            short[1].entries.pop();
+            let mut over = plan.split_by(SplitRule::PackType);
+            let extra = over[0].entries[0].clone();
+            over[1].entries.push(extra);
+            let short = split_branch(&dir, &status_dir, "trunk", named(short), "pat", "");
+            let over = split_branch(&dir, &status_dir, "trunk", named(over), "pat", "");
+            let ghost = named(plan.split_by(SplitRule::PackType));
+            let ghost = split_branch(&dir, &status_dir, "ghost", ghost, "pat", "");
+            let groups = named(plan.split_by(SplitRule::PackType));
+            let moves = split_branch(&dir, &status_dir, "trunk", groups, "pat", "")?;
+            let whole = plan.split_by(SplitRule::Condition);
+            let whole = whole.into_iter().map(|x| (String::from("new"), x)).collect();
+            let refused = split_branch(&dir, &status_dir, "trunk", whole, "pat", "");
+            let refused = [refused, ghost, short, over];
+            Ok((moves, refused, Ledger::load_all(&dir)?, Status::load_all(&status_dir)?))
+        };
+        let ran = run();
+        std::fs::remove_dir_all(&dir)?;
+        let (moves, refused, ledgers, statuses) = ran?;
+
+        assert_eq!(moves.len(), 2);
+        assert!(refused.iter().all(|x| x.is_err()));
+        assert!(!ledgers.contains_key("ghost"));
+        assert_eq!(ledgers["trunk"].units(), 0);
+        assert_eq!((ledgers["0"].units(), ledgers["1"].units()), (8, 8));
+        assert_eq!(ledgers["0"].transactions()[0].note, "Mixed Fnskus");
+        assert_eq!(statuses["trunk"], Status::Closed);
+        assert_eq!(statuses["1"], Status::Open);
+        Ok(())
+    }
+
+    #[test]
+    fn partial_portions() -> Result<()> {
+        use portion::Portion;
+
+        let mut plan = (0..10)
+            .map(|x| entry("aaa", &format!("case-{x}"), 30))
+            .collect::<Vec<_>>();
+        let packed = plan.clone();
+        plan.push(entry("aaa", "loose", 10));
+        plan.push(entry("bbb", "loose", 3));
+
+        let units = |taken: Result<Vec<Entry>>| taken.map(|x| (x.len(), x.units()));
+        // Only a subset of the cases adds up, rather than the largest first.
+        let uneven = vec![entry("aaa", "a", 30), entry("aaa", "b", 20), entry("aaa", "c", 20)];
+        assert_eq!(units(uneven.portion("aaa", &Portion::Units(40)))?, (2, 40));
+        assert!(uneven.portion("aaa", &Portion::Units(45)).is_err());
+        // A staging group of a single Fnsku is loose when it was imported so.
+        let mut staged = entry("aaa", "staging", 12);
+        assert!(vec![staged.clone()].portion("aaa", &Portion::Units(5)).is_err());
+        staged.set_loose(true);
+        assert_eq!(units(vec![staged].portion("aaa", &Portion::Units(5)))?, (1, 5));
+
+        assert_eq!(units(plan.portion("aaa", &Portion::Units(120)))?, (4, 120));
+        assert_eq!(units(plan.portion("aaa", &Portion::Units(125)))?, (5, 125));
+        assert!(packed.portion("aaa", &Portion::Units(125)).is_err());
+        assert!(plan.portion("aaa", &Portion::Units(311)).is_err());
+        assert!(plan.portion("aaa", &Portion::Units(0)).is_err());
+
+        let cases = Portion::Cases(vec![String::from("case-2"), String::from("loose")]);
+        assert_eq!(units(plan.portion("aaa", &cases))?, (2, 40));
+        assert!(plan.portion("bbb", &cases).is_err());
+
+        // Selected cases move whole, with every Fnsku that they hold.
+        let ids = BTreeSet::from([String::from("case-2"), String::from("loose")]);
+        assert_eq!(units(plan.take_cases(&ids))?, (3, 43));
+        assert!(plan.take_cases(&BTreeSet::new()).is_err());
+        let gone = BTreeSet::from([String::from("case-2"), String::from("case-99")]);
+        assert!(plan.take_cases(&gone).is_err());
+
+        // Moved units are negated, so a second portion only sees what remains.
+        let taken = plan.portion("aaa", &Portion::Units(125))?;
+        plan.extend(taken.as_negated());
+        assert_eq!(plan.units_of_skus()["aaa"], 185);
+        assert!(plan.portion("aaa", &Portion::Units(185)).is_ok());
+        let loose = plan.portion("aaa", &Portion::Cases(vec![String::from("loose")]));
+        assert_eq!(units(loose)?, (1, 5));
+        Ok(())
+    }
+
+    #[test]
+    fn ledger_violations() {
+        use validate::Violation;
+
+        let mut plan = vec![entry("aaa", "case-1", 6), entry("bbb", "case-2", 4)];
+        assert!(plan.validate().is_empty());
+
+        // The same negation written twice.
+        plan.push(entry("aaa", "case-1", -6));
+        plan.push(entry("aaa", "case-1", -6));
+        assert_eq!(plan.negative_unit_case_count(), 1);
+        plan.push(entry("ccc", "case-3", -1));
+        plan.push(entry("ddd", "case-2", 2));
+        let violations = plan.validate();
+        assert_eq!(violations.len(), 4);
+        assert!(violations.contains(&Violation::NegativeCase {
+            case: String::from("case-1"),
+            fnsku: String::from("aaa"),
+            units: -6,
+        }));
+        assert!(violations.contains(&Violation::NegativeFnsku {
+            fnsku: String::from("ccc"),
+            units: -1,
+        }));
+        assert!(violations.contains(&Violation::UnmatchedNegation {
+            case: String::from("case-3"),
+            fnsku: String::from("ccc"),
+            units: -1,
+        }));
+        assert!(!violations.iter().any(|x| matches!(x, Violation::CaseChanged { .. })));
+
+        // A case is only changed once units have left it.
+        plan.push(entry("bbb", "case-2", -1));
+        plan.push(entry("eee", "case-2", 1));
+        let changed = Violation::CaseChanged {
+            case: String::from("case-2"),
+            added: vec![String::from("eee")],
+        };
+        assert!(plan.validate().contains(&changed));
+        assert!(status::Status::Check.permits(&plan).is_err());
+    }
+
+    #[test]
+    fn plan_diff() {
+        let before = vec![entry("aaa", "case-1", 6), entry("aaa", "case-2", 6)];
+        let mut after = before.clone();
+        after.push(entry("aaa", "case-2", -6));
+        after.push(entry("aaa", "case-3", 6));
+        after.push(entry("bbb", "case-3", 2));
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.fnskus.len(), 1);
+        assert_eq!(diff.fnskus["bbb"].delta(), 2);
+        assert_eq!(diff.cases.len(), 2);
+        assert_eq!(diff.cases["case-2"]["aaa"].after, 0);
+        assert_eq!(diff.cases["case-3"].len(), 2);
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn hazmat_mixing() {
+        use hazmat::Hazmat;
+        use status::Status;
+
+        assert_eq!("Flammables".parse(), Ok(Hazmat::Flammable));
+        assert_eq!("--".parse(), Ok(Hazmat::NonHazmat));
+        assert_eq!("Lithium Ion".parse(), Ok(Hazmat::Other));
+
+        let mut spray = Entry::default();
+        spray.set_fnsku(String::from("aaa"));
+        spray.set_units(4);
+        spray.set_hazmat(Some(Hazmat::Aerosol));
+        let mut other = Entry::default();
+        other.set_fnsku(String::from("bbb"));
+        other.set_units(6);
+
+        let mixed = vec![spray.clone(), other.clone()];
+        assert!(mixed.mixes_hazmat());
+        assert_eq!(mixed.hazmat_entries(true).len(), 1);
+        assert!(Status::Check.permits(&mixed).is_err());
+        assert!(Status::Open.permits(&mixed).is_ok());
+
+        // Moving the hazmat out negates it, which is no longer mixed.
+        let mut moved = mixed.clone();
+        moved.extend(vec![spray.clone()].as_negated());
+        assert!(!moved.mixes_hazmat());
+        assert!(Status::Check.permits(&moved).is_ok());
+        assert!(!vec![spray.clone()].mixes_hazmat());
+
+        // A move out written without a classification is still hazmat.
+        let mut unclassified = spray.clone();
+        unclassified.set_units(-1);
+        unclassified.set_hazmat(None);
+        let hazmat_only = vec![spray, unclassified];
+        assert!(!hazmat_only.mixes_hazmat());
+        assert_eq!(hazmat_only.hazmat_fnskus(), BTreeMap::from([("aaa", true)]));
+        assert!(Status::Check.permits(&hazmat_only).is_ok());
+
+        // The check file notes each Fnsku that is hazmat or has no condition.
+        other.set_condition(Some(Condition::New));
+        let rows = status::CheckRow::by_fnsku(&vec![hazmat_only[0].clone(), other]);
+        assert_eq!(rows["aaa"].notes(), "No condition; Hazmat");
+        assert_eq!(rows["bbb"].notes(), "");
+    }
     #[test]
     fn serialize() {
         let mut entry1 = Entry::default();
@@ -0,0 +1,90 @@
+use super::{Entry, Plan};
+use crate::{catalog::Catalog, Branch, Fnsku};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The units of an Fnsku planned across branches, and the units available.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Allocation {
+    pub fnsku: Fnsku,
+    /// Planned units of each branch that holds the Fnsku.
+    pub branches: BTreeMap<Branch, i32>,
+    /// Sellable units of the inventory report, `None` when not listed.
+    pub available: Option<u32>,
+}
+
+impl Allocation {
+    /// Returns the units planned across every branch.
+    pub fn planned(&self) -> i32 {
+        self.branches.values().sum()
+    }
+
+    /// Returns the units planned beyond what is available.
+    ///
+    /// An Fnsku that is missing from the inventory report has nothing
+    /// available.
+    pub fn shortfall(&self) -> u32 {
+        let planned = self.planned().max(0) as u32;
+        planned.saturating_sub(self.available.unwrap_or_default())
+    }
+
+    pub fn is_over_allocated(&self) -> bool {
+        self.shortfall() > 0
+    }
+}
+
+/// Planned units compared against warehouse availability, for each Fnsku.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvailabilityReport {
+    /// Sorted by Fnsku.
+    pub allocations: Vec<Allocation>,
+}
+
+impl AvailabilityReport {
+    /// Sum the units of each of the `branches`, by Fnsku.
+    ///
+    /// Available units are read from the inventory report within `catalog`.
+    pub fn new<'a, I>(branches: I, catalog: &Catalog) -> Self
+    where
+        I: IntoIterator<Item = (&'a Branch, &'a Vec<Entry>)>,
+    {
+        let mut planned: BTreeMap<Fnsku, BTreeMap<Branch, i32>> = BTreeMap::new();
+        for (branch, entries) in branches {
+            for (fnsku, units) in entries.units_of_skus() {
+                if units == 0 {
+                    continue;
+                };
+                let by_branch = planned.entry(fnsku.to_string()).or_default();
+                *by_branch.entry(branch.to_owned()).or_default() += units;
+            }
+        }
+        let allocations = planned
+            .into_iter()
+            .map(|(fnsku, branches)| {
+                let available = catalog.get_by_fnsku(&fnsku).and_then(|x| x.get_available());
+                Allocation {
+                    fnsku,
+                    branches,
+                    available,
+                }
+            })
+            .collect();
+        Self { allocations }
+    }
+
+    /// Returns every [`Allocation`] that plans more than is available.
+    pub fn over_allocated(&self) -> Vec<&Allocation> {
+        self.allocations
+            .iter()
+            .filter(|x| x.is_over_allocated())
+            .collect()
+    }
+
+    /// Returns the over allocated Fnskus that are planned within `branch`.
+    pub fn over_allocated_in(&self, branch: &str) -> Vec<&Allocation> {
+        self.over_allocated()
+            .into_iter()
+            .filter(|x| x.branches.contains_key(branch))
+            .collect()
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{bail, Error};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+/// Product conditions for an Asin.
+///
+/// Amazon reports use several codings for a condition, all of which are
+/// accepted when parsing, see [`Condition::from_code`] and [`FromStr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Condition {
+    New,
+    UsedLikeNew,
+    UsedVeryGood,
+    UsedGood,
+    UsedAcceptable,
+    CollectibleLikeNew,
+    CollectibleVeryGood,
+    CollectibleGood,
+    CollectibleAcceptable,
+    Refurbished,
+}
+
+impl Condition {
+    /// Every condition.
+    pub const ALL: [Condition; 10] = [
+        Condition::New,
+        Condition::UsedLikeNew,
+        Condition::UsedVeryGood,
+        Condition::UsedGood,
+        Condition::UsedAcceptable,
+        Condition::CollectibleLikeNew,
+        Condition::CollectibleVeryGood,
+        Condition::CollectibleGood,
+        Condition::CollectibleAcceptable,
+        Condition::Refurbished,
+    ];
+
+    /// Translate Amz's numeric coding of item conditions.
+    ///
+    /// These are used by the All Listings Report, code `9` is unused.
+    pub fn from_code(code: u32) -> Option<Condition> {
+        let condition = match code {
+            1 => Condition::UsedLikeNew,
+            2 => Condition::UsedVeryGood,
+            3 => Condition::UsedGood,
+            4 => Condition::UsedAcceptable,
+            5 => Condition::CollectibleLikeNew,
+            6 => Condition::CollectibleVeryGood,
+            7 => Condition::CollectibleGood,
+            8 => Condition::CollectibleAcceptable,
+            10 => Condition::Refurbished,
+            11 => Condition::New,
+            _ => return None,
+        };
+        Some(condition)
+    }
+
+    /// Returns the numeric code of [`Self`], see [`Self::from_code`].
+    pub fn code(&self) -> u32 {
+        match self {
+            Condition::UsedLikeNew => 1,
+            Condition::UsedVeryGood => 2,
+            Condition::UsedGood => 3,
+            Condition::UsedAcceptable => 4,
+            Condition::CollectibleLikeNew => 5,
+            Condition::CollectibleVeryGood => 6,
+            Condition::CollectibleGood => 7,
+            Condition::CollectibleAcceptable => 8,
+            Condition::Refurbished => 10,
+            Condition::New => 11,
+        }
+    }
+
+    /// Returns the name of [`Self`] as shown to sellers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Condition::New => "New",
+            Condition::UsedLikeNew => "Used - Like New",
+            Condition::UsedVeryGood => "Used - Very Good",
+            Condition::UsedGood => "Used - Good",
+            Condition::UsedAcceptable => "Used - Acceptable",
+            Condition::CollectibleLikeNew => "Collectible - Like New",
+            Condition::CollectibleVeryGood => "Collectible - Very Good",
+            Condition::CollectibleGood => "Collectible - Good",
+            Condition::CollectibleAcceptable => "Collectible - Acceptable",
+            Condition::Refurbished => "Refurbished",
+        }
+    }
+
+    /// Returns true if [`Self`] is any of the used conditions.
+    pub fn is_used(&self) -> bool {
+        matches!(
+            self,
+            Condition::UsedLikeNew
+                | Condition::UsedVeryGood
+                | Condition::UsedGood
+                | Condition::UsedAcceptable
+        )
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Parses numeric codes, the names of [`Condition::as_str`], and the
+/// inventory report's names, e.g. `NewItem` or `UsedLikeNew`.
+impl FromStr for Condition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(code) = s.trim().parse::<u32>() {
+            return match Condition::from_code(code) {
+                Some(condition) => Ok(condition),
+                None => bail!("Unknown item condition code '{code}'"),
+            };
+        };
+        let squashed = s
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let condition = match squashed.as_str() {
+            "new" | "newitem" => Condition::New,
+            "usedlikenew" => Condition::UsedLikeNew,
+            "usedverygood" => Condition::UsedVeryGood,
+            "usedgood" => Condition::UsedGood,
+            "usedacceptable" => Condition::UsedAcceptable,
+            "collectiblelikenew" => Condition::CollectibleLikeNew,
+            "collectibleverygood" => Condition::CollectibleVeryGood,
+            "collectiblegood" => Condition::CollectibleGood,
+            "collectibleacceptable" => Condition::CollectibleAcceptable,
+            "refurbished" | "refurbisheditem" => Condition::Refurbished,
+            _ => bail!("Unknown item condition '{s}'"),
+        };
+        Ok(condition)
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Condition> for String {
+    fn from(value: Condition) -> Self {
+        format!("{value:?}")
+    }
+}
+
+/// Deserialize an optional [`Condition`], treating unknown values as none.
+///
+/// Used for records written before conditions were typed.
+pub(crate) fn deserialize_lenient<'de, D>(deserializer: D) -> Result<Option<Condition>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.and_then(|x| x.parse().ok()))
+}
@@ -0,0 +1,117 @@
+use super::{Entry, Plan};
+use crate::{catalog::Field, Fnsku};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The units of something in two states of a branch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitChange {
+    pub before: i32,
+    pub after: i32,
+}
+
+impl UnitChange {
+    /// Returns the units gained, negative when units were lost.
+    pub fn delta(&self) -> i32 {
+        self.after - self.before
+    }
+}
+
+/// Every difference in units between two states of a branch.
+///
+/// Only Fnskus and cases whose units changed are listed, see [`PlanDiff::new`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanDiff {
+    /// Sorted by Fnsku.
+    pub fnskus: BTreeMap<Fnsku, UnitChange>,
+    /// Keyed by case id, then by each Fnsku within the case.
+    pub cases: BTreeMap<String, BTreeMap<Fnsku, UnitChange>>,
+}
+
+impl PlanDiff {
+    /// Compare the units of `before` and `after`, by Fnsku and by case.
+    pub fn new<A, B>(before: &A, after: &B) -> Self
+    where
+        A: Plan + ?Sized,
+        B: Plan + ?Sized,
+    {
+        let mut fnskus: BTreeMap<Fnsku, UnitChange> = BTreeMap::new();
+        let mut cases: BTreeMap<String, BTreeMap<Fnsku, UnitChange>> = BTreeMap::new();
+        let mut tally = |entry: &Entry, side: fn(&mut UnitChange) -> &mut i32| {
+            let fnsku = entry.get_fnsku().to_string();
+            *side(fnskus.entry(fnsku.clone()).or_default()) += entry.get_units();
+            let case = cases.entry(entry.get_id().to_string()).or_default();
+            *side(case.entry(fnsku).or_default()) += entry.get_units();
+        };
+        before.iter().for_each(|x| tally(x, |x| &mut x.before));
+        after.iter().for_each(|x| tally(x, |x| &mut x.after));
+
+        fnskus.retain(|_, x| x.delta() != 0);
+        cases
+            .values_mut()
+            .for_each(|x| x.retain(|_, x| x.delta() != 0));
+        cases.retain(|_, x| !x.is_empty());
+        Self { fnskus, cases }
+    }
+
+    /// Returns true if no units changed.
+    pub fn is_empty(&self) -> bool {
+        self.fnskus.is_empty() && self.cases.is_empty()
+    }
+}
+
+/// An enriched [`Field`] of an Fnsku that two plans disagree on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnrichmentMismatch {
+    pub fnsku: Fnsku,
+    pub field: Field,
+    pub ours: String,
+    pub theirs: String,
+}
+
+impl EnrichmentMismatch {
+    /// Compare the enrichment of every Fnsku held by both `ours` and `theirs`.
+    ///
+    /// Only fields that were enriched within both plans are compared, see
+    /// [`Entry::enrichment`]. Sorted by Fnsku, then by field.
+    pub fn between<A, B>(ours: &A, theirs: &B) -> Vec<Self>
+    where
+        A: Plan + ?Sized,
+        B: Plan + ?Sized,
+    {
+        let theirs = enrichment_by_fnsku(theirs);
+        let mut mismatches = vec![];
+        for (fnsku, fields) in enrichment_by_fnsku(ours) {
+            let Some(other) = theirs.get(&fnsku) else {
+                continue;
+            };
+            for (field, ours) in fields {
+                match other.get(&field) {
+                    Some(theirs) if theirs != &ours => mismatches.push(Self {
+                        fnsku: fnsku.to_string(),
+                        field,
+                        ours,
+                        theirs: theirs.clone(),
+                    }),
+                    _ => (),
+                };
+            }
+        }
+        mismatches
+    }
+}
+
+/// Returns the enriched fields of each Fnsku, the first entry to enrich a field wins.
+fn enrichment_by_fnsku<P>(plan: &P) -> BTreeMap<&str, BTreeMap<Field, String>>
+where
+    P: Plan + ?Sized,
+{
+    let mut fnskus: BTreeMap<&str, BTreeMap<Field, String>> = BTreeMap::new();
+    for entry in plan.iter() {
+        let fields = fnskus.entry(entry.get_fnsku()).or_default();
+        for (field, value) in entry.enrichment() {
+            fields.entry(field).or_insert(value);
+        }
+    }
+    fnskus
+}
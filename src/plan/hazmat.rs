@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+/// Dangerous goods classification of an Fnsku.
+///
+/// Read from the `dangerous_goods_storage_type` of the Monthly Storage Fees
+/// report. Amazon routes hazmat to different shipments than everything else,
+/// see [`Hazmat::is_hazmat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Hazmat {
+    NonHazmat,
+    Flammable,
+    Aerosol,
+    /// Any other dangerous goods storage type.
+    Other,
+}
+
+impl Hazmat {
+    /// Returns true for every classification but [`Hazmat::NonHazmat`].
+    pub fn is_hazmat(&self) -> bool {
+        !matches!(self, Hazmat::NonHazmat)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Hazmat::NonHazmat => "Non-Hazmat",
+            Hazmat::Flammable => "Flammable",
+            Hazmat::Aerosol => "Aerosol",
+            Hazmat::Other => "Hazmat",
+        }
+    }
+}
+
+impl Display for Hazmat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Parses a dangerous goods storage type, which never fails.
+///
+/// Empty values and placeholders such as `--` are [`Hazmat::NonHazmat`],
+/// storage types that are not known are [`Hazmat::Other`].
+impl FromStr for Hazmat {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let squashed = s
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        let hazmat = match squashed.as_str() {
+            "" | "na" | "none" | "nonhazmat" => Hazmat::NonHazmat,
+            "flammable" | "flammables" => Hazmat::Flammable,
+            "aerosol" | "aerosols" => Hazmat::Aerosol,
+            _ => Hazmat::Other,
+        };
+        Ok(hazmat)
+    }
+}
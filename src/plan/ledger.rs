@@ -0,0 +1,685 @@
+use super::{
+    diff::{EnrichmentMismatch, PlanDiff},
+    split::SplitGroup,
+    status::Status,
+    summary::Summary,
+    Entry, Plan,
+};
+use crate::{Branch, Brn};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+/// What a [`Transaction`] did to its branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransactionKind {
+    /// Entries read from a plan or report.
+    Import,
+    /// Entries moved to another branch, recorded as negated units.
+    MoveOut,
+    /// Entries moved in from another branch.
+    MoveIn,
+    /// Units added or removed by hand.
+    Adjustment,
+    /// Reverses an earlier transaction that was recorded in error.
+    Correction,
+}
+
+impl Display for TransactionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionKind::Import => write!(f, "Import"),
+            TransactionKind::MoveOut => write!(f, "Move Out"),
+            TransactionKind::MoveIn => write!(f, "Move In"),
+            TransactionKind::Adjustment => write!(f, "Adjustment"),
+            TransactionKind::Correction => write!(f, "Correction"),
+        }
+    }
+}
+
+/// The transaction on the other side of a move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Link {
+    pub branch: Branch,
+    pub transaction: String,
+}
+
+/// A single change to a branch, along with who made it and why.
+///
+/// Each transaction is written to its own `{branch}_{id}.json` file, a
+/// branch is the sum of the entries of its transactions, see [`Ledger`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    pub id: String,
+    pub branch: Branch,
+    pub kind: TransactionKind,
+    /// Milliseconds since the unix epoch.
+    pub time: u64,
+    pub operator: String,
+    pub note: String,
+    /// Read from a file written before the ledger, see [`Transaction::read`].
+    #[serde(skip)]
+    pub legacy: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<Link>,
+    /// The id of the transaction that [`Self`] reverses, see [`revert_move`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverts: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+impl Transaction {
+    /// A [`Transaction`] of `entries` on `branch`, made now by the
+    /// [`default_operator`].
+    pub fn new(branch: Brn, kind: TransactionKind, entries: Vec<Entry>) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            branch: branch.to_string(),
+            kind,
+            time: now(),
+            operator: default_operator(),
+            note: String::new(),
+            legacy: false,
+            link: None,
+            reverts: None,
+            entries,
+        }
+    }
+
+    /// An empty `operator` keeps the [`default_operator`].
+    pub fn with_operator(mut self, operator: &str) -> Self {
+        if !operator.trim().is_empty() {
+            self.operator = operator.trim().to_string();
+        };
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.note = note.trim().to_string();
+        self
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}_{}.json", self.branch, self.id)
+    }
+
+    /// Read the transaction written at `path`.
+    ///
+    /// Files written before the ledger hold a bare list of entries, these are
+    /// read as an import, or as a move out when every unit is negated. Their
+    /// time is the modification time of the file, which copying rewrites, so a
+    /// [`Ledger`] orders them ahead of every other transaction.
+    ///
+    /// # Errors
+    ///
+    /// * The file cannot be read.
+    /// * The file name is not `{branch}_{id}.json`.
+    /// * The file holds neither a transaction nor a list of entries.
+    pub fn read<P>(path: P) -> Result<Transaction>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)?;
+        if let Ok(transaction) = serde_json::from_str(&json) {
+            return Ok(transaction);
+        };
+        let entries: Vec<Entry> = serde_json::from_str(&json)
+            .with_context(|| format!("{} is not a transaction", path.display()))?;
+        let (branch, id) = path
+            .file_stem()
+            .and_then(|x| x.to_str())
+            .and_then(|x| x.split_once('_'))
+            .with_context(|| format!("{} is not named by its branch", path.display()))?;
+        let negated = !entries.is_empty() && entries.iter().all(|x| x.get_units() < 0);
+        let kind = match negated {
+            true => TransactionKind::MoveOut,
+            false => TransactionKind::Import,
+        };
+        let modified = std::fs::metadata(path)?.modified()?;
+        Ok(Transaction {
+            id: id.to_string(),
+            branch: branch.to_string(),
+            kind,
+            time: modified.duration_since(UNIX_EPOCH)?.as_millis() as u64,
+            operator: String::new(),
+            note: String::from("Recorded before the ledger"),
+            legacy: true,
+            link: None,
+            reverts: None,
+            entries,
+        })
+    }
+
+    /// Returns the units of every entry within [`Self`].
+    pub fn units(&self) -> i32 {
+        self.entries.units()
+    }
+}
+
+impl Plan for Transaction {
+    fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+}
+
+/// Write each of the `transactions` within `dir`, all or none of them.
+///
+/// Every file is written in full before any of them are renamed into place,
+/// and a failed rename removes the files already renamed, so that a failed
+/// write never leaves half of a move behind.
+///
+/// # Errors
+///
+/// The underlying file system fails at writing to `dir`.
+pub fn write_all<P>(dir: P, transactions: &[&Transaction]) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let mut staged: Vec<(PathBuf, PathBuf)> = vec![];
+    let discard = |staged: &[(PathBuf, PathBuf)]| {
+        staged.iter().for_each(|(tmp, _)| {
+            let _ = std::fs::remove_file(tmp);
+        });
+    };
+    for transaction in transactions {
+        let path = dir.join(transaction.file_name());
+        let tmp = path.with_extension("json.tmp");
+        let written = serde_json::to_string_pretty(transaction)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&tmp, json)?));
+        if let Err(err) = written {
+            discard(&staged);
+            return Err(err);
+        };
+        staged.push((tmp, path));
+    }
+    for (idx, (tmp, path)) in staged.iter().enumerate() {
+        if let Err(err) = std::fs::rename(tmp, path) {
+            staged[..idx].iter().for_each(|(_, path)| {
+                let _ = std::fs::remove_file(path);
+            });
+            discard(&staged[idx..]);
+            return Err(err.into());
+        };
+    }
+    Ok(())
+}
+
+/// Mark each branch of `marks` within `status_dir`, once `transactions` are
+/// written to `dir`, see [`write_all`].
+///
+/// When any mark fails, the marks made so far and the files of
+/// `transactions` are removed, so the change is undone as a whole.
+///
+/// # Errors
+///
+/// A status cannot be marked, the change was undone.
+pub fn mark_written<P, Q>(
+    dir: P,
+    status_dir: Q,
+    transactions: &[&Transaction],
+    marks: &[(Status, Brn)],
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let mut marked = vec![];
+    for (status, branch) in marks {
+        match Status::mark(status, &status_dir, branch) {
+            Ok(path) => marked.push(path),
+            Err(err) => {
+                let written = transactions.iter().map(|x| dir.as_ref().join(x.file_name()));
+                for path in marked.into_iter().chain(written) {
+                    let _ = std::fs::remove_file(path);
+                }
+                let context = format!("{branch} could not be marked {status}, nothing was moved");
+                return Err(err.context(context));
+            }
+        };
+    }
+    Ok(())
+}
+
+/// Move `entries` from the branch `from` into the branch `to`.
+///
+/// The source records the entries negated, the destination records them as
+/// they are, and each [`Transaction`] links to the other. Both are written
+/// together, see [`write_all`].
+///
+/// # Errors
+///
+/// The underlying file system fails at writing to `dir`.
+pub fn move_entries<P>(
+    dir: P,
+    from: Brn,
+    to: Brn,
+    entries: Vec<Entry>,
+    operator: &str,
+    note: &str,
+) -> Result<(Transaction, Transaction)>
+where
+    P: AsRef<Path>,
+{
+    let mut move_out = Transaction::new(from, TransactionKind::MoveOut, entries.as_negated())
+        .with_operator(operator)
+        .with_note(note);
+    let mut move_in = Transaction::new(to, TransactionKind::MoveIn, entries)
+        .with_operator(operator)
+        .with_note(note);
+    link(&mut move_out, &mut move_in);
+    write_all(dir, &[&move_out, &move_in])?;
+    Ok((move_out, move_in))
+}
+
+/// Link `a` and `b` to each other, as the two sides of a single change.
+fn link(a: &mut Transaction, b: &mut Transaction) {
+    b.time = a.time;
+    a.link = Some(Link {
+        branch: b.branch.clone(),
+        transaction: b.id.clone(),
+    });
+    b.link = Some(Link {
+        branch: a.branch.clone(),
+        transaction: a.id.clone(),
+    });
+}
+
+/// Reverse the move that the transaction `id` of `branch` is a side of.
+///
+/// The units return to the source, and the destination records them moving
+/// back out, each as a [`TransactionKind::Correction`]. The destination is
+/// marked [`Status::Closed`] within `status_dir` when nothing remains on it.
+/// An empty `note` describes the reverted move instead.
+///
+/// # Errors
+///
+/// * `id` is not a move of `branch`, or the move was already reverted.
+/// * The source is no longer open, such as a branch closed by
+///   [`merge_branches`], as a closed branch cannot hold units again.
+/// * The destination has been marked for checking.
+/// * Units of the move have since left the destination.
+/// * The underlying file system fails at reading or writing.
+pub fn revert_move<P, Q>(
+    dir: P,
+    status_dir: Q,
+    branch: Brn,
+    id: &str,
+    operator: &str,
+    note: &str,
+) -> Result<(Transaction, Transaction)>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let ledgers = Ledger::load_all(dir)?;
+    let find = |branch: &str, id: &str| ledgers.get(branch).and_then(|x| x.get(id));
+    let Some(transaction) = find(branch, id) else {
+        bail!("{branch} has no transaction {id}");
+    };
+    let other = transaction
+        .link
+        .as_ref()
+        .and_then(|x| find(&x.branch, &x.transaction));
+    let (move_out, move_in) = match (transaction.kind, other) {
+        (TransactionKind::MoveOut, Some(other)) => (transaction, other),
+        (TransactionKind::MoveIn, Some(other)) => (other, transaction),
+        _ => bail!("Only moves between branches can be reverted"),
+    };
+    let (source, destination) = (&move_out.branch, &move_in.branch);
+
+    let reverted = ledgers.values().flat_map(|x| x.transactions()).any(|x| {
+        let reverts = x.reverts.as_deref();
+        reverts == Some(move_out.id.as_str()) || reverts == Some(move_in.id.as_str())
+    });
+    if reverted {
+        bail!("This move from {source} to {destination} was already reverted");
+    };
+    let statuses = Status::load_all(&status_dir)?;
+    let status = |branch: Brn| statuses.get(branch).copied().unwrap_or_default();
+    let source_status = status(source);
+    if source_status > Status::Open {
+        bail!("{source} is marked {source_status}, units can no longer be returned to it");
+    };
+    let status = status(destination);
+    if status >= Status::Check {
+        bail!("{destination} is marked {status}, it can no longer be reverted");
+    };
+
+    let mut remaining = ledgers[destination].entries();
+    remaining.extend(move_in.as_negated());
+    let short = remaining
+        .units_of_skus()
+        .into_iter()
+        .filter(|(_, units)| units < &0)
+        .map(|(fnsku, _)| fnsku)
+        .collect::<Vec<_>>();
+    if !short.is_empty() {
+        bail!("{} have since left {destination}", short.join(", "));
+    };
+
+    let note = match note.is_empty() {
+        true => format!("Reverts the move from {source} to {destination}"),
+        false => note.to_string(),
+    };
+    let correction = |branch: Brn, reverts: &Transaction| {
+        let mut correction =
+            Transaction::new(branch, TransactionKind::Correction, reverts.as_negated())
+                .with_operator(operator)
+                .with_note(&note);
+        correction.reverts = Some(reverts.id.clone());
+        correction
+    };
+    let closes = remaining.units_of_skus().values().all(|x| x == &0);
+    if closes {
+        Status::Closed.permits(&remaining)?;
+    };
+    let mut returned = correction(source, move_out);
+    let mut removed = correction(destination, move_in);
+    link(&mut returned, &mut removed);
+    write_all(dir, &[&returned, &removed])?;
+    let marks = match closes {
+        true => vec![(Status::Closed, destination.as_str())],
+        false => vec![],
+    };
+    mark_written(dir, status_dir, &[&returned, &removed], &marks)?;
+    Ok((returned, removed))
+}
+
+/// A branch that was emptied into another, see [`merge_branches`].
+#[derive(Debug, Clone)]
+pub struct Merge {
+    pub move_out: Transaction,
+    pub move_in: Transaction,
+    /// Fnskus held by both branches that were enriched differently.
+    pub mismatches: Vec<EnrichmentMismatch>,
+}
+
+/// Move every remaining entry of `from` into `into`, then close `from`.
+///
+/// Both branches must be [`Status::Open`] within `status_dir`. The merge is
+/// recorded as a move, see [`move_entries`]. An empty `note` names the
+/// branch that was merged into instead.
+///
+/// # Errors
+///
+/// * `from` and `into` are the same branch, or either is not open.
+/// * Nothing remains on `from`, or its ledger is broken, see [`Plan::validate`].
+/// * The underlying file system fails at reading or writing.
+pub fn merge_branches<P, Q>(
+    dir: P,
+    status_dir: Q,
+    from: Brn,
+    into: Brn,
+    operator: &str,
+    note: &str,
+) -> Result<Merge>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    if from == into {
+        bail!("{from} cannot be merged into itself");
+    };
+    let statuses = Status::load_all(&status_dir)?;
+    for branch in [from, into] {
+        let status = statuses.get(branch).copied().unwrap_or_default();
+        if status != Status::Open {
+            bail!("{branch} is marked {status}, only open branches can be merged");
+        };
+    }
+    let (source, destination) = (Ledger::load(&dir, from)?, Ledger::load(&dir, into)?);
+    Status::Closed.permits(&source)?;
+    let remaining = source.remaining();
+    if remaining.is_empty() {
+        bail!("Nothing remains on {from} to merge");
+    };
+
+    let mismatches = destination.enrichment_mismatches(&remaining);
+    let note = match note.is_empty() {
+        true => format!("Merged into {into}"),
+        false => note.to_string(),
+    };
+    let (move_out, move_in) = move_entries(&dir, from, into, remaining, operator, &note)?;
+    let marks = [(Status::Closed, from)];
+    mark_written(dir, status_dir, &[&move_out, &move_in], &marks)?;
+    Ok(Merge {
+        move_out,
+        move_in,
+        mismatches,
+    })
+}
+
+/// Move each group of `from` to a new branch of its own, then close `from`.
+///
+/// `groups` pairs each new branch with the entries it receives, as divided
+/// by a [`super::split::SplitRule`]. Every move is written together, see
+/// [`write_all`], and the new branches are marked [`Status::Open`].
+///
+/// # Errors
+///
+/// * `from` is not open, has no ledger, or its ledger is broken, see
+///   [`Plan::validate`].
+/// * Fewer than two groups are given.
+/// * The groups do not add up to what remains on `from`, by case and Fnsku.
+/// * A new branch already has transactions.
+/// * The underlying file system fails at reading or writing.
+pub fn split_branch<P, Q>(
+    dir: P,
+    status_dir: Q,
+    from: Brn,
+    groups: Vec<(Branch, SplitGroup)>,
+    operator: &str,
+    note: &str,
+) -> Result<Vec<(Transaction, Transaction)>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let status = Status::load_all(&status_dir)?
+        .get(from)
+        .copied()
+        .unwrap_or_default();
+    if status != Status::Open {
+        bail!("{from} is marked {status}, only open branches can be split");
+    };
+    if groups.len() < 2 {
+        bail!("Every entry of {from} falls into the same group, nothing to split");
+    };
+    let ledgers = Ledger::load_all(&dir)?;
+    if let Some((branch, _)) = groups.iter().find(|(x, _)| ledgers.contains_key(x)) {
+        bail!("{branch} already exists, it cannot receive a split");
+    };
+    let Some(source) = ledgers.get(from) else {
+        bail!("{from} has no ledger, there is nothing to split");
+    };
+
+    let mut moves = vec![];
+    for (branch, group) in groups {
+        let note = match note.is_empty() {
+            true => group.label,
+            false => format!("{note} ({})", group.label),
+        };
+        let negated = group.entries.as_negated();
+        let mut move_out = Transaction::new(from, TransactionKind::MoveOut, negated)
+            .with_operator(operator)
+            .with_note(&note);
+        let mut move_in = Transaction::new(&branch, TransactionKind::MoveIn, group.entries)
+            .with_operator(operator)
+            .with_note(&note);
+        link(&mut move_out, &mut move_in);
+        moves.push((move_out, move_in));
+    }
+    // The balance of `from` once every group has moved out.
+    let mut after = source.entries();
+    after.extend(moves.iter().flat_map(|(x, _)| x.entries.iter().cloned()));
+    if !after.remaining().is_empty() {
+        bail!("The groups do not add up to what remains on {from}, by case and Fnsku");
+    };
+    Status::Closed.permits(&after)?;
+    let written = moves.iter().flat_map(|(a, b)| [a, b]).collect::<Vec<_>>();
+    write_all(&dir, &written)?;
+    let mut marks = moves
+        .iter()
+        .map(|(_, x)| (Status::Open, x.branch.as_str()))
+        .collect::<Vec<_>>();
+    marks.push((Status::Closed, from));
+    mark_written(dir, status_dir, &written, &marks)?;
+    Ok(moves)
+}
+
+/// Every [`Transaction`] of a single branch, oldest first.
+///
+/// The entries of a branch are derived from its ledger, see [`Plan`].
+#[derive(Debug, Default, Clone)]
+pub struct Ledger {
+    branch: Branch,
+    transactions: Vec<Transaction>,
+    /// Filled the first time it is read, see [`Ledger::summary`].
+    summary: OnceLock<Summary>,
+}
+
+impl Ledger {
+    /// Read the ledger of `branch` from `dir`.
+    ///
+    /// A branch without any transactions has an empty ledger.
+    ///
+    /// # Errors
+    ///
+    /// The underlying file system fails at reading `dir`.
+    pub fn load<P>(dir: P, branch: Brn) -> Result<Ledger>
+    where
+        P: AsRef<Path>,
+    {
+        let mut all = Ledger::load_all(dir)?;
+        let ledger = all.remove(branch).unwrap_or_else(|| Ledger {
+            branch: branch.to_string(),
+            ..Default::default()
+        });
+        Ok(ledger)
+    }
+
+    /// Read the ledger of every branch within `dir`.
+    ///
+    /// Json files that are not transactions are skipped.
+    ///
+    /// # Errors
+    ///
+    /// The underlying file system fails at reading `dir`.
+    pub fn load_all<P>(dir: P) -> Result<HashMap<Branch, Ledger>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut ledgers: HashMap<Branch, Ledger> = HashMap::new();
+        for file in std::fs::read_dir(dir)?.filter_map(|x| x.ok()) {
+            let path = file.path();
+            if path.extension().and_then(|x| x.to_str()) != Some("json") {
+                continue;
+            };
+            let Ok(transaction) = Transaction::read(&path) else {
+                continue;
+            };
+            let branch = transaction.branch.clone();
+            let ledger = ledgers.entry(branch.clone()).or_insert_with(|| Ledger {
+                branch,
+                ..Default::default()
+            });
+            ledger.transactions.push(transaction);
+        }
+        ledgers.values_mut().for_each(|x| x.sort());
+        Ok(ledgers)
+    }
+
+    /// Legacy transactions come first, imports ahead of moves out.
+    fn sort(&mut self) {
+        let key = |x: &Transaction| (!x.legacy, x.legacy && x.kind != TransactionKind::Import);
+        self.transactions
+            .sort_by(|a, b| (key(a), a.time, &a.id).cmp(&(key(b), b.time, &b.id)));
+        self.summary = OnceLock::new();
+    }
+
+    /// Returns the aggregates of the branch, computed once, see [`Summary`].
+    pub fn summary(&self) -> &Summary {
+        self.summary.get_or_init(|| self.summarize())
+    }
+
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// Returns each [`Transaction`], oldest first.
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// Returns the transaction with the given `id`, if there is one.
+    pub fn get(&self, id: &str) -> Option<&Transaction> {
+        self.transactions.iter().find(|x| x.id == id)
+    }
+
+    /// Returns the entries of the branch after its first `count` transactions.
+    ///
+    /// A `count` of `0` is the empty branch, before anything was recorded.
+    pub fn upto(&self, count: usize) -> Vec<Entry> {
+        self.transactions
+            .iter()
+            .take(count)
+            .flat_map(|x| x.entries.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the entries of the branch right after the transaction `id`.
+    pub fn at(&self, id: &str) -> Option<Vec<Entry>> {
+        let idx = self.transactions.iter().position(|x| x.id == id)?;
+        Some(self.upto(idx + 1))
+    }
+
+    /// Returns the entries of the branch as it was at `time`, in milliseconds
+    /// since the unix epoch.
+    ///
+    /// Legacy transactions have no reliable time and are always included.
+    pub fn as_of(&self, time: u64) -> Vec<Entry> {
+        let count = self
+            .transactions
+            .iter()
+            .take_while(|x| x.legacy || x.time <= time)
+            .count();
+        self.upto(count)
+    }
+
+    /// Returns what the transaction `id` changed, see [`PlanDiff`].
+    pub fn changes_of(&self, id: &str) -> Option<PlanDiff> {
+        let idx = self.transactions.iter().position(|x| x.id == id)?;
+        Some(self.upto(idx).diff(&self.upto(idx + 1)))
+    }
+}
+
+impl Plan for Ledger {
+    fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.transactions.iter().flat_map(|x| x.entries.iter())
+    }
+}
+
+/// Returns the milliseconds since the unix epoch.
+fn now() -> u64 {
+    let since = SystemTime::now().duration_since(UNIX_EPOCH);
+    since.map(|x| x.as_millis() as u64).unwrap_or_default()
+}
+
+/// The name of the user running Plaine, read from the environment.
+pub fn default_operator() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| String::from("unknown"))
+}
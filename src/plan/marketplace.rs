@@ -0,0 +1,152 @@
+use crate::{
+    read::{LengthUnit, VolumeUnit, WeightUnit},
+    Branch, Brn,
+};
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, path::Path, str::FromStr};
+
+/// The Amazon marketplace that a branch is shipped to.
+///
+/// Reports name a marketplace by its country code, see [`FromStr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Marketplace {
+    US,
+    CA,
+    MX,
+    BR,
+    UK,
+    DE,
+    FR,
+    IT,
+    ES,
+    NL,
+    SE,
+    PL,
+    JP,
+    AU,
+    IN,
+}
+
+impl Marketplace {
+    /// Every marketplace.
+    pub const ALL: [Marketplace; 15] = [
+        Marketplace::US,
+        Marketplace::CA,
+        Marketplace::MX,
+        Marketplace::BR,
+        Marketplace::UK,
+        Marketplace::DE,
+        Marketplace::FR,
+        Marketplace::IT,
+        Marketplace::ES,
+        Marketplace::NL,
+        Marketplace::SE,
+        Marketplace::PL,
+        Marketplace::JP,
+        Marketplace::AU,
+        Marketplace::IN,
+    ];
+
+    /// Returns the ISO 4217 code of the currency that fees are charged in.
+    pub fn currency(&self) -> &'static str {
+        match self {
+            Marketplace::US => "USD",
+            Marketplace::CA => "CAD",
+            Marketplace::MX => "MXN",
+            Marketplace::BR => "BRL",
+            Marketplace::UK => "GBP",
+            Marketplace::DE | Marketplace::FR | Marketplace::IT => "EUR",
+            Marketplace::ES | Marketplace::NL => "EUR",
+            Marketplace::SE => "SEK",
+            Marketplace::PL => "PLN",
+            Marketplace::JP => "JPY",
+            Marketplace::AU => "AUD",
+            Marketplace::IN => "INR",
+        }
+    }
+
+    /// Returns true if reports of [`Self`] are in imperial units.
+    fn is_imperial(&self) -> bool {
+        matches!(self, Marketplace::US)
+    }
+
+    /// The unit of length used when a report does not name one.
+    pub fn length_unit(&self) -> LengthUnit {
+        match self.is_imperial() {
+            true => LengthUnit::Inches,
+            false => LengthUnit::Centimeters,
+        }
+    }
+
+    /// The unit of weight used when a report does not name one.
+    pub fn weight_unit(&self) -> WeightUnit {
+        match self.is_imperial() {
+            true => WeightUnit::Pounds,
+            false => WeightUnit::Kilograms,
+        }
+    }
+
+    /// The unit of volume used when a report does not name one.
+    pub fn volume_unit(&self) -> VolumeUnit {
+        match self.is_imperial() {
+            true => VolumeUnit::CubicFeet,
+            false => VolumeUnit::CubicMeters,
+        }
+    }
+
+    /// Record `marketplace` as the target of `branch`, within `path`.
+    ///
+    /// A branch only has a single marketplace, marking it again replaces it.
+    pub fn mark<P>(marketplace: &Marketplace, path: P, branch: Brn) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        std::fs::create_dir_all(&path)?;
+        let full_filename = path.as_ref().join(format!("{branch}.json"));
+        let json = serde_json::to_string(marketplace)?;
+        Ok(std::fs::write(full_filename, json)?)
+    }
+
+    /// Read the [`Marketplace`] of each branch marked within `path`.
+    ///
+    /// A missing `path` has no marked branches.
+    pub fn load_all<P>(path: P) -> Result<HashMap<Branch, Marketplace>>
+    where
+        P: AsRef<Path>,
+    {
+        let Ok(dir) = std::fs::read_dir(path) else {
+            return Ok(HashMap::default());
+        };
+        let marks = dir.filter_map(|x| x.ok()).filter_map(|x| {
+            let path = x.path();
+            let branch = path.file_stem()?.to_str()?.to_string();
+            let json = std::fs::read_to_string(&path).ok()?;
+            Some((branch, serde_json::from_str(&json).ok()?))
+        });
+        Ok(marks.collect())
+    }
+}
+
+impl Display for Marketplace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Parses the country codes used by reports, `GB` is read as [`Marketplace::UK`].
+impl FromStr for Marketplace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_ascii_uppercase();
+        let found = match code.as_str() {
+            "GB" => Some(Marketplace::UK),
+            code => Marketplace::ALL.into_iter().find(|x| x.to_string() == code),
+        };
+        match found {
+            Some(marketplace) => Ok(marketplace),
+            None => bail!("Unknown marketplace '{s}'"),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use super::{Entry, Plan};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Part of the remaining units of an Fnsku, see [`Portion::take`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Portion {
+    /// A count of units, made of whole cases wherever the Fnsku is case packed.
+    Units(i32),
+    /// Every unit of the Fnsku within each of these case ids.
+    Cases(Vec<String>),
+}
+
+impl Portion {
+    /// Returns the entries of `fnsku` within `plan` that make up [`Self`].
+    ///
+    /// Cases holding only `fnsku` are packed and are only ever taken whole,
+    /// as many units as possible. Cases that mix Fnskus and entries imported
+    /// as loose, see [`Entry::is_loose`], make up whatever the packed cases
+    /// fall short by.
+    ///
+    /// # Errors
+    ///
+    /// * No case is given, or a case does not hold any units of `fnsku`.
+    /// * The count is not positive, is more than remains, or cannot be made
+    ///   from whole cases.
+    pub fn take<P>(&self, plan: &P, fnsku: &str) -> Result<Vec<Entry>>
+    where
+        P: Plan + ?Sized,
+    {
+        let remaining = plan.remaining();
+        let mixed = remaining
+            .multi_fnsku_cases()
+            .into_iter()
+            .map(|x| x.get_id().to_string())
+            .collect::<BTreeSet<_>>();
+        let remaining = remaining
+            .into_iter()
+            .filter(|x| x.get_fnsku() == fnsku && x.get_units() > 0)
+            .collect::<Vec<_>>();
+        match self {
+            Portion::Cases(ids) => {
+                let ids = ids.iter().map(String::as_str).collect::<BTreeSet<_>>();
+                if ids.is_empty() {
+                    bail!("Choose at least one case of {fnsku}");
+                };
+                let taken = remaining
+                    .into_iter()
+                    .filter(|x| ids.contains(x.get_id()))
+                    .collect::<Vec<_>>();
+                let found = taken.iter().map(|x| x.get_id()).collect::<BTreeSet<_>>();
+                let missing = ids.difference(&found).copied().collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    bail!("{} hold no units of {fnsku}", missing.join(", "));
+                };
+                Ok(taken)
+            }
+            Portion::Units(units) => take_units(remaining, &mixed, fnsku, *units),
+        }
+    }
+}
+
+/// Returns every remaining entry of the cases `ids` within `plan`, so that
+/// each case is taken whole, whichever Fnskus it holds.
+///
+/// # Errors
+///
+/// No case is given, or a case no longer holds any units.
+pub fn take_cases<P>(plan: &P, ids: &BTreeSet<String>) -> Result<Vec<Entry>>
+where
+    P: Plan + ?Sized,
+{
+    if ids.is_empty() {
+        bail!("Select at least one case to move");
+    };
+    let taken = plan
+        .remaining()
+        .into_iter()
+        .filter(|x| x.get_units() > 0 && ids.contains(x.get_id()))
+        .collect::<Vec<_>>();
+    let found = taken.iter().map(|x| x.get_id()).collect::<BTreeSet<_>>();
+    let missing = ids
+        .iter()
+        .map(String::as_str)
+        .filter(|x| !found.contains(x))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        bail!("{} no longer hold any units", missing.join(", "));
+    };
+    Ok(taken)
+}
+
+/// Take `units` of `remaining`, where `mixed` are the ids of loose cases.
+fn take_units(
+    remaining: Vec<Entry>,
+    mixed: &BTreeSet<String>,
+    fnsku: &str,
+    units: i32,
+) -> Result<Vec<Entry>> {
+    let total = remaining.units();
+    if units <= 0 || units > total {
+        bail!("{units} units of {fnsku} cannot be moved, {total} remain");
+    };
+    let (mut loose, mut packed): (Vec<_>, Vec<_>) = remaining
+        .into_iter()
+        .partition(|x| x.is_loose() || mixed.contains(x.get_id()));
+    packed.sort_by(|a, b| (b.get_units(), a.get_id()).cmp(&(a.get_units(), b.get_id())));
+    loose.sort_by(|a, b| a.get_id().cmp(b.get_id()));
+
+    let sizes = packed.iter().map(|x| x.get_units()).collect::<Vec<_>>();
+    let cases = whole_cases(&sizes, units);
+    let mut needed = units - cases.iter().map(|&idx| sizes[idx]).sum::<i32>();
+    let mut taken = cases
+        .into_iter()
+        .map(|idx| packed[idx].clone())
+        .collect::<Vec<_>>();
+    for mut entry in loose {
+        if needed == 0 {
+            break;
+        };
+        let units = entry.get_units().min(needed);
+        needed -= units;
+        entry.set_units(units);
+        taken.push(entry);
+    }
+    if needed > 0 {
+        bail!(
+            "{units} units of {fnsku} cannot be moved in whole cases, {} could be",
+            units - needed
+        );
+    };
+    Ok(taken)
+}
+
+/// Returns the indexes of the `sizes` that add up to the most units, without
+/// going over `units`. Earlier sizes are preferred when sums tie.
+fn whole_cases(sizes: &[i32], units: i32) -> Vec<usize> {
+    // The last size added to reach each sum, and the sum it was added to.
+    let mut reached: Vec<Option<(usize, usize)>> = vec![None; units as usize + 1];
+    let mut sums = vec![0];
+    for (idx, &size) in sizes.iter().enumerate() {
+        let size = size as usize;
+        let mut new = vec![];
+        for &sum in sums.iter() {
+            let next = sum + size;
+            if next <= units as usize && next != 0 && reached[next].is_none() {
+                reached[next] = Some((idx, sum));
+                new.push(next);
+            };
+        }
+        sums.extend(new);
+    }
+    let mut sum = sums.into_iter().max().unwrap_or_default();
+    let mut cases = vec![];
+    while let Some((idx, prev)) = reached[sum] {
+        cases.push(idx);
+        sum = prev;
+    }
+    cases.reverse();
+    cases
+}
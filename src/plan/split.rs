@@ -0,0 +1,133 @@
+use super::{Entry, Plan};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display};
+
+/// A rule that divides the entries of a branch into groups.
+///
+/// Every rule keeps each case whole. A case whose Fnskus disagree under a
+/// rule about an Fnsku is placed in a mixed group of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SplitRule {
+    /// Standard size against oversize, by the Amazon size tier.
+    SizeTier,
+    Condition,
+    /// Cases of at least `pounds` against lighter cases.
+    Weight {
+        pounds: f32,
+    },
+    /// Cases of a single Fnsku against cases that mix Fnskus.
+    PackType,
+}
+
+impl SplitRule {
+    /// Every rule, with a weight of 50 pounds.
+    pub const ALL: [SplitRule; 4] = [
+        SplitRule::SizeTier,
+        SplitRule::Condition,
+        SplitRule::Weight { pounds: 50.0 },
+        SplitRule::PackType,
+    ];
+
+    /// Divide the remaining entries of `plan`, see [`Plan::remaining`].
+    pub fn split<P>(&self, plan: &P) -> Vec<SplitGroup>
+    where
+        P: Plan + ?Sized,
+    {
+        let remaining = plan.remaining();
+        let mut groups: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+        match self {
+            SplitRule::SizeTier => {
+                by_case(&remaining, "Mixed size tiers", size_tier, &mut groups);
+            }
+            SplitRule::Condition => {
+                let condition = |entry: &Entry| match entry.get_condition() {
+                    Some(condition) => condition.to_string(),
+                    None => String::from("Unknown condition"),
+                };
+                by_case(&remaining, "Mixed conditions", condition, &mut groups);
+            }
+            SplitRule::Weight { pounds } => {
+                for case in remaining.as_group_by_case().into_values() {
+                    let weight = case.iter().filter_map(|x| *x.get_total_pounds());
+                    let label = match weight.reduce(f32::max) {
+                        Some(weight) if weight >= *pounds => format!("At least {pounds} lb"),
+                        Some(_) => format!("Under {pounds} lb"),
+                        None => String::from("Unknown weight"),
+                    };
+                    groups
+                        .entry(label)
+                        .or_default()
+                        .extend(case.into_iter().cloned());
+                }
+            }
+            SplitRule::PackType => {
+                let packed = remaining.single_fnsku_cases().entries();
+                let mixed = remaining.multi_fnsku_cases().entries();
+                groups.insert(String::from("Single Fnsku"), packed);
+                groups.insert(String::from("Mixed Fnskus"), mixed);
+            }
+        };
+        groups
+            .into_iter()
+            .filter(|(_, entries)| !entries.is_empty())
+            .map(|(label, entries)| SplitGroup { label, entries })
+            .collect()
+    }
+}
+
+impl Display for SplitRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitRule::SizeTier => write!(f, "Size Tier"),
+            SplitRule::Condition => write!(f, "Condition"),
+            SplitRule::Weight { pounds } => write!(f, "Weight ({pounds} lb)"),
+            SplitRule::PackType => write!(f, "Pack Type"),
+        }
+    }
+}
+
+/// Group each case of `plan` by the `label` of its entries, or as `mixed`
+/// when they disagree. Entries without a case id are grouped on their own.
+fn by_case<F>(plan: &[Entry], mixed: &str, label: F, groups: &mut BTreeMap<String, Vec<Entry>>)
+where
+    F: Fn(&Entry) -> String,
+{
+    for (id, case) in plan.as_group_by_case() {
+        if id.is_empty() {
+            for entry in case {
+                groups.entry(label(entry)).or_default().push(entry.clone());
+            }
+            continue;
+        };
+        let mut labels = case.iter().map(|x| label(x));
+        let first = labels.next().unwrap_or_default();
+        let case_label = match labels.all(|x| x == first) {
+            true => first,
+            false => mixed.to_string(),
+        };
+        groups
+            .entry(case_label)
+            .or_default()
+            .extend(case.into_iter().cloned());
+    }
+}
+
+/// Returns `Oversize` for any tier above standard size.
+fn size_tier(entry: &Entry) -> String {
+    let Some(tier) = entry.get_amz_size() else {
+        return String::from("Unknown size");
+    };
+    let tier = tier.to_lowercase();
+    let oversize = ["oversize", "bulky", "extra-large"];
+    match oversize.into_iter().any(|x| tier.contains(x)) {
+        true => String::from("Oversize"),
+        false => String::from("Standard"),
+    }
+}
+
+/// Entries that a [`SplitRule`] placed together.
+#[derive(Debug, Clone)]
+pub struct SplitGroup {
+    pub label: String,
+    pub entries: Vec<Entry>,
+}
@@ -1,10 +1,14 @@
 #![allow(dead_code)]
-use std::{fmt::Display, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
 use crate::{Branch, Brn};
 
 use super::{Entry, Plan};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -15,7 +19,10 @@ struct StatusRecord {
 }
 
 impl Status {
-    pub fn mark<P>(status: &Status, path: P, branch: Brn) -> Result<()>
+    /// Mark `branch` as `status` within `path`.
+    ///
+    /// Returns the file of the mark, removing it undoes the mark.
+    pub fn mark<P>(status: &Status, path: P, branch: Brn) -> Result<PathBuf>
     where
         P: AsRef<Path>,
     {
@@ -25,24 +32,85 @@ impl Status {
         full_filename.push(full);
 
         let json = serde_json::to_string(&status)?;
-        Ok(std::fs::write(full_filename, json)?)
+        std::fs::write(&full_filename, json)?;
+        Ok(full_filename)
     }
 
+    /// Read the current [`Status`] of each branch marked within `path`.
+    ///
+    /// A branch that was marked more than once takes its greatest status.
+    ///
+    /// # Errors
+    ///
+    /// The underlying file system fails at reading `path`.
+    pub fn load_all<P>(path: P) -> Result<HashMap<Branch, Status>>
+    where
+        P: AsRef<Path>,
+    {
+        let reads = std::fs::read_dir(path)?.filter_map(|dir| dir.ok());
+        let mut marks = reads
+            .filter_map(|x| {
+                let str = std::fs::read_to_string(x.path()).ok()?;
+                let status: Status = serde_json::from_str(&str).ok()?;
+                let name = x.file_name();
+                let (branch, _) = name.to_str()?.split_once('_')?;
+                Some((branch.to_string(), status))
+            })
+            .collect::<Vec<_>>();
+        marks.sort();
+        Ok(marks.into_iter().collect())
+    }
 
+    /// Returns an error if `plan` cannot be marked as [`Self`].
+    ///
+    /// No branch can change status while its ledger is broken, see
+    /// [`Plan::validate`]. A branch that mixes hazmat and non-hazmat units
+    /// cannot be checked, as Amazon routes them to separate shipments.
+    pub fn permits<P: Plan>(&self, plan: &P) -> Result<()> {
+        let violations = plan.validate();
+        if let Some(violation) = violations.first() {
+            bail!(
+                "Cannot mark {self}, the ledger has {} problems: {violation}",
+                violations.len()
+            );
+        };
+        if matches!(self, Status::Check) && plan.mixes_hazmat() {
+            let hazmat = plan.hazmat_fnskus().into_iter().filter(|(_, x)| *x);
+            let fnskus = hazmat.map(|(fnsku, _)| fnsku).collect::<Vec<_>>();
+            bail!(
+                "Hazmat cannot share a branch with other items, move {} to a new branch",
+                fnskus.join(", ")
+            );
+        };
+        Ok(())
+    }
+
+    /// Returns true if branches in [`Self`] still hold their inventory.
+    ///
+    /// A [`Status::Closed`] branch has given up all of its units.
+    pub fn is_active(&self) -> bool {
+        matches!(self, Status::Open | Status::Check)
+    }
 }
 
 /// The current stage of progress for [`Plan`]s.
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Clone, Copy)]
 pub enum Status {
+    /// Generated from a report, and not yet accepted by a planner.
+    Draft,
     #[default]
     Open,
     Check,
+    /// Emptied into another branch, nothing is left to ship.
+    Closed,
 }
 impl Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Status::Draft => write!(f, "Draft"),
             Status::Open => write!(f, "Open"),
             Status::Check => write!(f, "Check"),
+            Status::Closed => write!(f, "Closed"),
         }
     }
 }
@@ -57,9 +125,29 @@ pub enum Warn {
     Weight(Vec<Entry>),
     Dimesions(Vec<Entry>),
     MissingInfo(Vec<Entry>),
+    Condition(Vec<Entry>),
+    Hazmat(Vec<Entry>),
     Special,
     Forbid,
 }
+impl Display for Warn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warn::Upc => write!(f, "Upc does not match"),
+            Warn::Cases => write!(f, "Cases do not match"),
+            Warn::Sku => write!(f, "Sku does not match"),
+            Warn::Units => write!(f, "Units do not match"),
+            Warn::TeamLift(_) => write!(f, "Team lift"),
+            Warn::Weight(_) => write!(f, "Over 49 pounds"),
+            Warn::Dimesions(_) => write!(f, "Over 24 inches"),
+            Warn::MissingInfo(_) => write!(f, "Missing dimensions or weight"),
+            Warn::Condition(_) => write!(f, "No condition"),
+            Warn::Hazmat(_) => write!(f, "Hazmat"),
+            Warn::Special => write!(f, "Special"),
+            Warn::Forbid => write!(f, "Forbidden"),
+        }
+    }
+}
 
 type Warnings = Option<Vec<Warn>>;
 type Upc = Option<String>;
@@ -85,6 +173,36 @@ impl CheckRow<Vec<Entry>> {
             ..Default::default()
         }
     }
+
+    /// Returns a [`Self`] for each Fnsku of `plan`, keyed by Fnsku.
+    pub fn by_fnsku<P: Plan>(plan: &P) -> BTreeMap<String, Self> {
+        let mut rows: BTreeMap<String, Self> = BTreeMap::new();
+        for entry in plan.iter() {
+            let fnsku = entry.get_fnsku().to_owned();
+            let row = rows.entry(fnsku).or_insert_with(|| Self::new(vec![]));
+            row.predicate.push(entry.clone());
+        }
+        rows
+    }
+
+    /// Returns the notes of a check file row, see [`Self::plan_checks`].
+    pub fn notes(&self) -> String {
+        let notes = self.plan_checks().iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        notes.join("; ")
+    }
+
+    /// Returns [`Warn`]ings of the predicate in [`Self`] that are known
+    /// before anything is scanned.
+    fn plan_checks(&self) -> Vec<Warn> {
+        let mut warnings = vec![];
+        if let Some(no_condition) = self.missing_condition() {
+            warnings.push(Warn::Condition(no_condition));
+        };
+        if let Some(hazmat) = self.hazmat() {
+            warnings.push(Warn::Hazmat(hazmat));
+        };
+        warnings
+    }
     /// Checks [`Self`] and returns [`Warn`]ings of issues.
     ///
     /// See [`Warn`] for a description of the attributes being checked for.
@@ -115,6 +233,7 @@ impl CheckRow<Vec<Entry>> {
             let info = Warn::MissingInfo(needs_info);
             warnings.push(info)
         }
+        warnings.extend(self.plan_checks());
         Some(warnings)
     }
 
@@ -122,13 +241,13 @@ impl CheckRow<Vec<Entry>> {
     fn cases_need_team_lift(&self) -> Option<Vec<Entry>> {
         let team_lift: Vec<_> = self
             .predicate
-            .entries()
-            .into_iter()
+            .iter()
             .filter(|case| {
                 let weight = case.get_total_pounds().unwrap_or_default();
                 let units = case.get_units();
                 units.eq(&1) && weight.gt(&49.00)
             })
+            .cloned()
             .collect();
         (!team_lift.is_empty()).then_some(team_lift)
     }
@@ -137,13 +256,13 @@ impl CheckRow<Vec<Entry>> {
     fn cases_too_heavy(&self) -> Option<Vec<Entry>> {
         let scary_dims: Vec<_> = self
             .predicate
-            .entries()
-            .into_iter()
+            .iter()
             .filter(|case| {
                 let weight = case.get_total_pounds().unwrap_or_default();
                 let units = case.get_units();
                 units.gt(&1) && weight.gt(&49.00)
             })
+            .cloned()
             .collect();
         (!scary_dims.is_empty()).then_some(scary_dims)
     }
@@ -152,12 +271,12 @@ impl CheckRow<Vec<Entry>> {
     fn cases_too_long(&self) -> Option<Vec<Entry>> {
         let scary_dims: Vec<_> = self
             .predicate
-            .entries()
-            .into_iter()
+            .iter()
             .filter(|case| {
                 let dims = case.get_case_dimensions().unwrap_or_default();
                 dims.into_iter().any(|dim| dim > 24.00)
             })
+            .cloned()
             .collect();
         (!scary_dims.is_empty()).then_some(scary_dims)
     }
@@ -166,17 +285,35 @@ impl CheckRow<Vec<Entry>> {
     fn needs_info(&self) -> Option<Vec<Entry>> {
         let missing_info: Vec<_> = self
             .predicate
-            .entries()
-            .into_iter()
+            .iter()
             .filter(|case| {
                 let missing_dims = case.get_case_dimensions().is_none();
                 let missing_weight = case.get_total_pounds().is_none();
                 missing_dims || missing_weight
             })
+            .cloned()
             .collect();
         (!missing_info.is_empty()).then_some(missing_info)
     }
 
+    /// Returns entries without a known condition.
+    fn missing_condition(&self) -> Option<Vec<Entry>> {
+        let unknown = self.predicate.with_condition(None);
+        (!unknown.is_empty()).then(|| unknown.entries())
+    }
+
+    /// Returns entries of the Fnskus that are hazmat, see [`Plan::hazmat_fnskus`].
+    fn hazmat(&self) -> Option<Vec<Entry>> {
+        let fnskus = self.predicate.hazmat_fnskus();
+        let hazmat: Vec<_> = self
+            .predicate
+            .iter()
+            .filter(|x| fnskus.get(x.get_fnsku()) == Some(&true))
+            .cloned()
+            .collect();
+        (!hazmat.is_empty()).then_some(hazmat)
+    }
+
     /// Returns true if the number of cases matches the predicate in [`Self`].
     fn same_num_cases(&self) -> bool {
         let Some(input) = self.cases else {return false};
@@ -196,8 +333,7 @@ impl CheckRow<Vec<Entry>> {
     fn same_upc(&self) -> bool {
         let input_upc = &self.upc;
         self.predicate
-            .entries()
-            .into_iter()
+            .iter()
             .any(|x| input_upc == x.get_upc())
     }
 }
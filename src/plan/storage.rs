@@ -0,0 +1,273 @@
+use super::{marketplace::Marketplace, Plan};
+use crate::{
+    catalog::{Catalog, Product},
+    Fnsku,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Monthly storage fees, per cubic foot, of a single size tier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TierRate {
+    /// Compared against the size tier of the Monthly Storage Fees report,
+    /// without regard to case or punctuation.
+    pub size_tier: String,
+    pub off_peak: f32,
+    pub peak: f32,
+}
+
+/// The storage fees of a single marketplace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateTable {
+    pub marketplace: Marketplace,
+    /// Months, `1` through `12`, that are charged the peak rate.
+    pub peak_months: Vec<u32>,
+    pub tiers: Vec<TierRate>,
+}
+
+impl RateTable {
+    /// Returns the rates of `size_tier`, if there are any.
+    pub fn tier(&self, size_tier: &str) -> Option<&TierRate> {
+        let squash = |s: &str| {
+            s.chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_ascii_lowercase()
+        };
+        let size_tier = squash(size_tier);
+        self.tiers
+            .iter()
+            .find(|x| squash(&x.size_tier) == size_tier)
+    }
+
+    /// Returns true if `month` is charged the peak rate.
+    pub fn is_peak(&self, month: u32) -> bool {
+        self.peak_months.contains(&month)
+    }
+
+    /// Returns the off peak and peak rate of `product`.
+    ///
+    /// Size tiers without a rate in [`Self`] fall back to the rate Amazon
+    /// last charged, which is only known for peak or off peak months.
+    fn rates(&self, product: &Product) -> (Option<f32>, Option<f32>) {
+        let tier = product.get_amz_size().and_then(|x| self.tier(x));
+        if let Some(tier) = tier {
+            return (Some(tier.off_peak), Some(tier.peak));
+        };
+        match product.get_storage_rate() {
+            Some((month, rate)) if self.is_peak(month.month().into()) => (None, Some(rate)),
+            Some((_, rate)) => (Some(rate), None),
+            None => (None, None),
+        }
+    }
+}
+
+/// The config table of storage fees, read from a json file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageRates {
+    pub tables: Vec<RateTable>,
+}
+
+impl Default for StorageRates {
+    /// Amazon's published fees for the US marketplace.
+    fn default() -> Self {
+        let us = RateTable {
+            marketplace: Marketplace::US,
+            peak_months: vec![10, 11, 12],
+            tiers: vec![
+                TierRate {
+                    size_tier: String::from("Standard-Size"),
+                    off_peak: 0.78,
+                    peak: 2.40,
+                },
+                TierRate {
+                    size_tier: String::from("Oversize"),
+                    off_peak: 0.56,
+                    peak: 1.40,
+                },
+            ],
+        };
+        Self { tables: vec![us] }
+    }
+}
+
+impl StorageRates {
+    /// Read the config table at `path`, or the default rates when missing.
+    ///
+    /// # Errors
+    ///
+    /// The file exists, but is not a valid table.
+    pub fn load<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Returns the table of `marketplace`, a marketplace of `None` is the US.
+    pub fn table(&self, marketplace: Option<Marketplace>) -> Option<&RateTable> {
+        let marketplace = marketplace.unwrap_or(Marketplace::US);
+        self.tables.iter().find(|x| x.marketplace == marketplace)
+    }
+}
+
+/// The projected monthly storage cost of a single Fnsku.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageCost {
+    pub fnsku: Fnsku,
+    pub units: i32,
+    pub size_tier: Option<String>,
+    /// Total volume of every unit, in cubic feet.
+    pub cubic_feet: Option<f32>,
+    pub off_peak: Option<f32>,
+    pub peak: Option<f32>,
+    /// What Amazon last charged for as many units, to check the estimate by.
+    pub charged: Option<f32>,
+}
+
+impl StorageCost {
+    /// Returns true if neither cost could be estimated.
+    pub fn is_unknown(&self) -> bool {
+        self.off_peak.is_none() && self.peak.is_none()
+    }
+}
+
+/// The projected monthly storage cost of a plan, once it is received.
+///
+/// Volumes and size tiers are read from the Monthly Storage Fees report,
+/// see [`StorageEstimate::new`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageEstimate {
+    pub currency: String,
+    /// See [`RateTable::peak_months`].
+    pub peak_months: Vec<u32>,
+    /// Sorted by Fnsku.
+    pub costs: Vec<StorageCost>,
+}
+
+impl StorageEstimate {
+    /// Estimate the monthly storage cost of the units of `plan`.
+    ///
+    /// Fnskus without a volume or a known size tier are still listed, but
+    /// their costs are left unknown. See [`RateTable::rates`].
+    ///
+    /// # Errors
+    ///
+    /// `rates` does not have a table for `marketplace`.
+    pub fn new<P: Plan>(
+        plan: &P,
+        catalog: &Catalog,
+        rates: &StorageRates,
+        marketplace: Option<Marketplace>,
+    ) -> Result<Self> {
+        let table = rates.table(marketplace).ok_or_else(|| {
+            let name = marketplace.unwrap_or(Marketplace::US);
+            anyhow!("There are no storage rates for the {name} marketplace")
+        })?;
+        let mut costs = plan
+            .units_of_skus()
+            .into_iter()
+            .filter(|(_, units)| units > &0)
+            .map(|(fnsku, units)| {
+                let product = catalog.get_by_fnsku(fnsku);
+                let size_tier = product.and_then(|x| x.get_amz_size()).map(String::from);
+                let volume = product.and_then(|x| x.get_volume());
+                let cubic_feet = volume.map(|x| x * units as f32);
+                let (off_peak, peak) = product.map(|x| table.rates(x)).unwrap_or_default();
+                let fee = product.and_then(|x| x.get_unit_storage_fee());
+                StorageCost {
+                    off_peak: off_peak.and_then(|x| Some(cubic_feet? * x)),
+                    peak: peak.and_then(|x| Some(cubic_feet? * x)),
+                    charged: fee.map(|x| x * units as f32),
+                    fnsku: fnsku.to_string(),
+                    units,
+                    size_tier,
+                    cubic_feet,
+                }
+            })
+            .collect::<Vec<_>>();
+        costs.sort_by(|a, b| a.fnsku.cmp(&b.fnsku));
+        let currency = table.marketplace.currency().to_string();
+        Ok(Self {
+            currency,
+            peak_months: table.peak_months.clone(),
+            costs,
+        })
+    }
+
+    /// Returns the total cost of `month`, `1` through `12`, at its rate.
+    pub fn total_in(&self, month: u32) -> f32 {
+        match self.peak_months.contains(&month) {
+            true => self.total_peak(),
+            false => self.total_off_peak(),
+        }
+    }
+
+    /// Returns the total off peak cost of every known [`StorageCost`].
+    pub fn total_off_peak(&self) -> f32 {
+        self.costs.iter().filter_map(|x| x.off_peak).sum()
+    }
+
+    /// Returns the total peak cost of every known [`StorageCost`].
+    pub fn total_peak(&self) -> f32 {
+        self.costs.iter().filter_map(|x| x.peak).sum()
+    }
+
+    /// Returns the total of what Amazon last charged, see [`StorageCost::charged`].
+    pub fn total_charged(&self) -> f32 {
+        self.costs.iter().filter_map(|x| x.charged).sum()
+    }
+
+    /// Returns the Fnskus whose cost could not be estimated.
+    pub fn unknown(&self) -> Vec<&StorageCost> {
+        self.costs.iter().filter(|x| x.is_unknown()).collect()
+    }
+
+    /// Write each [`StorageCost`] as a row of csv, followed by a total row.
+    pub fn write_csv<W: std::io::Write>(&self, wtr: W) -> Result<()> {
+        let mut wtr = csv::Writer::from_writer(wtr);
+        let off_peak = format!("off_peak_{}", self.currency);
+        let peak = format!("peak_{}", self.currency);
+        let charged = format!("charged_{}", self.currency);
+        let header = [
+            "fnsku",
+            "units",
+            "size_tier",
+            "cubic_feet",
+            &off_peak,
+            &peak,
+            &charged,
+        ];
+        wtr.write_record(header)?;
+        let money = |x: Option<f32>| x.map(|x| format!("{x:.2}")).unwrap_or_default();
+        for cost in self.costs.iter() {
+            wtr.write_record([
+                cost.fnsku.clone(),
+                cost.units.to_string(),
+                cost.size_tier.clone().unwrap_or_default(),
+                cost.cubic_feet
+                    .map(|x| format!("{x:.3}"))
+                    .unwrap_or_default(),
+                money(cost.off_peak),
+                money(cost.peak),
+                money(cost.charged),
+            ])?;
+        }
+        let units: i32 = self.costs.iter().map(|x| x.units).sum();
+        wtr.write_record([
+            String::from("Total"),
+            units.to_string(),
+            String::new(),
+            String::new(),
+            money(Some(self.total_off_peak())),
+            money(Some(self.total_peak())),
+            money(Some(self.total_charged())),
+        ])?;
+        wtr.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use super::{condition::Condition, Entry, Plan};
+use std::collections::BTreeSet;
+
+/// The aggregates of a [`Plan`] that are shown on every frame.
+///
+/// Computed once by [`Plan::summarize`], after which reading any of them
+/// neither walks the plan nor allocates.
+#[derive(Debug, Default, Clone)]
+pub struct Summary {
+    /// Sorted by Fnsku, see [`Plan::get_as_sums`].
+    sums: Vec<Entry>,
+    /// The sums of the entries of each condition, see [`Summary::sums_of`].
+    condition_sums: Vec<(Option<Condition>, Vec<Entry>)>,
+    conditions: BTreeSet<Option<Condition>>,
+    /// Sorted by case id, see [`Summary::cases`].
+    cases: Vec<(String, Vec<Entry>)>,
+    units: i32,
+}
+
+impl Summary {
+    pub fn new<P>(plan: &P) -> Self
+    where
+        P: Plan + ?Sized,
+    {
+        let by_fnsku = |mut sums: Vec<Entry>| {
+            sums.sort_by(|a, b| a.get_fnsku().cmp(b.get_fnsku()));
+            sums
+        };
+        let conditions = plan.conditions();
+        let condition_sums = conditions
+            .iter()
+            .map(|x| (*x, by_fnsku(plan.with_condition(*x).get_as_sums())))
+            .collect();
+        let remaining = plan.remaining();
+        let mut cases = remaining
+            .as_group_by_case()
+            .into_iter()
+            .filter(|(_, case)| case.units() > 0)
+            .map(|(id, case)| (id.to_string(), case.into_iter().cloned().collect()))
+            .collect::<Vec<_>>();
+        cases.sort_by(|a, b| a.0.cmp(&b.0));
+        Self {
+            sums: by_fnsku(plan.get_as_sums()),
+            condition_sums,
+            conditions,
+            cases,
+            units: plan.units(),
+        }
+    }
+
+    /// Returns the units of each Fnsku, summed into a single entry.
+    pub fn sums(&self) -> &[Entry] {
+        &self.sums
+    }
+
+    /// Returns the units of each Fnsku of `condition`, summed into a single
+    /// entry. Only entries of `condition` are summed.
+    pub fn sums_of(&self, condition: Option<Condition>) -> &[Entry] {
+        self.condition_sums
+            .iter()
+            .find(|(x, _)| x == &condition)
+            .map(|(_, sums)| sums.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the units of `fnsku`, `0` when it is not planned.
+    pub fn units_of(&self, fnsku: &str) -> i32 {
+        let found = self.sums.binary_search_by(|x| x.get_fnsku().cmp(fnsku));
+        found
+            .map(|idx| self.sums[idx].get_units())
+            .unwrap_or_default()
+    }
+
+    /// Returns each distinct condition, see [`Plan::conditions`].
+    pub fn conditions(&self) -> &BTreeSet<Option<Condition>> {
+        &self.conditions
+    }
+
+    /// Returns every case that still holds units, by id.
+    ///
+    /// Each case is summed by Fnsku, see [`Plan::remaining`].
+    pub fn cases(&self) -> &[(String, Vec<Entry>)] {
+        &self.cases
+    }
+
+    pub fn units(&self) -> i32 {
+        self.units
+    }
+}
@@ -0,0 +1,124 @@
+use super::Plan;
+use crate::Fnsku;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+/// A record of a branch that its ledger should never hold.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Violation {
+    /// More units of `fnsku` were taken out of `case` than it ever held.
+    NegativeCase {
+        case: String,
+        fnsku: Fnsku,
+        units: i32,
+    },
+    /// More units of `fnsku` were taken out of the branch than it ever held.
+    NegativeFnsku { fnsku: Fnsku, units: i32 },
+    /// Units of `fnsku` were negated from `case`, which never held it.
+    UnmatchedNegation {
+        case: String,
+        fnsku: Fnsku,
+        units: i32,
+    },
+    /// Fnskus were added to `case` after units had been taken out of it.
+    CaseChanged { case: String, added: Vec<Fnsku> },
+}
+
+impl Violation {
+    /// Returns every [`Violation`] of `plan`, sorted.
+    ///
+    /// Balances are summed over every entry, while a case is changed only
+    /// when entries in order add an Fnsku to it after its first negation.
+    pub fn find<P>(plan: &P) -> Vec<Violation>
+    where
+        P: Plan + ?Sized,
+    {
+        let mut balances: BTreeMap<(&str, &str), i32> = BTreeMap::new();
+        let mut held: BTreeSet<(&str, &str)> = BTreeSet::new();
+        for entry in plan.iter() {
+            let key = (entry.get_id(), entry.get_fnsku());
+            *balances.entry(key).or_default() += entry.get_units();
+            if entry.get_units() > 0 {
+                held.insert(key);
+            };
+        }
+
+        let mut violations = vec![];
+        for (key, &units) in balances.iter().filter(|(_, units)| **units < 0) {
+            let (case, fnsku) = (key.0.to_string(), key.1.to_string());
+            violations.push(match held.contains(key) {
+                true => Violation::NegativeCase { case, fnsku, units },
+                false => Violation::UnmatchedNegation { case, fnsku, units },
+            });
+        }
+        let fnskus = plan.units_of_skus().into_iter();
+        violations.extend(
+            fnskus
+                .filter(|(_, units)| units < &0)
+                .map(|(fnsku, units)| Violation::NegativeFnsku {
+                    fnsku: fnsku.to_string(),
+                    units,
+                }),
+        );
+        violations.extend(changed_cases(plan));
+        violations.sort();
+        violations
+    }
+}
+
+/// Returns the cases that gained an Fnsku after their first negation.
+fn changed_cases<P>(plan: &P) -> Vec<Violation>
+where
+    P: Plan + ?Sized,
+{
+    let mut seen: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut negated: BTreeSet<&str> = BTreeSet::new();
+    let mut added: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for entry in plan.iter() {
+        let (case, fnsku) = (entry.get_id(), entry.get_fnsku());
+        let fnskus = seen.entry(case).or_default();
+        if negated.contains(case) && !fnskus.contains(fnsku) && entry.get_units() > 0 {
+            added.entry(case).or_default().insert(fnsku);
+        };
+        fnskus.insert(fnsku);
+        if entry.get_units() < 0 {
+            negated.insert(case);
+        };
+    }
+    added
+        .into_iter()
+        .map(|(case, fnskus)| Violation::CaseChanged {
+            case: case.to_string(),
+            added: fnskus.into_iter().map(String::from).collect(),
+        })
+        .collect()
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::NegativeCase { case, fnsku, units } => {
+                write!(f, "Case {case} holds {units} units of {fnsku}")
+            }
+            Violation::NegativeFnsku { fnsku, units } => {
+                write!(f, "The branch holds {units} units of {fnsku}")
+            }
+            Violation::UnmatchedNegation { case, fnsku, units } => {
+                write!(
+                    f,
+                    "{units} units of {fnsku} were taken from case {case}, which never held it"
+                )
+            }
+            Violation::CaseChanged { case, added } => {
+                write!(
+                    f,
+                    "Case {case} gained {} after units left it",
+                    added.join(", ")
+                )
+            }
+        }
+    }
+}
@@ -2,16 +2,47 @@
 mod tests;
 
 mod all_listings_report;
+mod archive;
+mod diagnostics;
+mod month;
+mod profile;
+mod registry;
+mod restock_inventory;
+mod units;
+mod workbook;
 
-use crate::{plan::Entry, utils::gen_pw_uuid};
+pub(crate) use archive::may_hold_report;
+pub use diagnostics::{ColumnError, Diagnostics, Rejection};
+pub use month::ReportMonth;
+pub use profile::{MappingProfile, COLUMNS, REQUIRED};
+pub(crate) use registry::read_products;
+pub use registry::{sniff, ReportKind, Sniffed};
+pub use restock_inventory::RestockPlan;
+pub use units::{LengthUnit, VolumeUnit, WeightUnit};
+pub use workbook::{is_workbook, sheet_names};
+
+use crate::{
+    catalog::{Catalog, EnrichmentReport, Product, Source},
+    plan::{condition::Condition, hazmat::Hazmat, marketplace::Marketplace, Entry},
+    utils::gen_pw_uuid,
+};
 use anyhow::{anyhow, bail, Error, Result};
-use serde::Deserialize;
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use self::{
+    registry::{rows, ParsedReport, Row},
+    units::parse_unit,
+};
 
-use self::all_listings_report::AllListingsReport;
-// Monthly Storage Fees report.
+/// Monthly Storage Fees report.
+///
+/// Once read, dimensions are always in inches, weight in pounds, and
+/// volume in cubic feet. See [`MonthlyStorageFees::normalize`].
 #[derive(serde::Deserialize, Debug, Default, Clone)]
-#[serde(deny_unknown_fields)]
 pub struct MonthlyStorageFees {
     #[serde(alias = "asin")]
     asin: Option<String>,
@@ -20,9 +51,9 @@ pub struct MonthlyStorageFees {
     #[serde(alias = "product_name")]
     product_name: Option<String>,
     #[serde(alias = "fulfillment_center")]
-    _fulfillment_center: Option<String>,
+    fulfillment_center: Option<String>,
     #[serde(alias = "country_code")]
-    _country_code: Option<String>,
+    country_code: Option<String>,
     #[serde(alias = "longest_side")]
     longest_side: Option<f32>,
     #[serde(alias = "median_side")]
@@ -30,33 +61,33 @@ pub struct MonthlyStorageFees {
     #[serde(alias = "shortest_side")]
     shortest_side: Option<f32>,
     #[serde(alias = "measurement_units")]
-    _measurement_units: Option<String>,
+    measurement_units: Option<String>,
     #[serde(alias = "weight")]
     weight: Option<f32>,
     #[serde(alias = "weight_units")]
-    _weight_units: Option<String>,
+    weight_units: Option<String>,
     #[serde(alias = "item_volume")]
-    _item_volume: Option<f32>,
+    item_volume: Option<f32>,
     #[serde(alias = "volume_units")]
-    _volume_units: Option<String>,
+    volume_units: Option<String>,
     #[serde(alias = "product_size_tier")]
     product_size_tier: Option<String>,
     #[serde(alias = "average_quantity_on_hand")]
-    _average_quantity_on_hand: Option<f32>,
+    average_quantity_on_hand: Option<f32>,
     #[serde(alias = "average_quantity_pending_removal")]
     _average_quantity_pending_removal: Option<f32>,
     #[serde(alias = "estimated_total_item_volume")]
-    _estimated_total_item_volume: Option<f32>,
+    estimated_total_item_volume: Option<f32>,
     #[serde(alias = "month_of_charge")]
-    _month_of_charge: Option<String>,
+    month_of_charge: Option<String>,
     #[serde(alias = "storage_rate")]
-    _storage_rate: Option<f32>,
+    storage_rate: Option<f32>,
     #[serde(alias = "currency")]
     _currency: Option<String>,
     #[serde(alias = "estimated_monthly_storage_fee")]
-    _estimated_monthly_storage_fee: Option<f32>,
+    estimated_monthly_storage_fee: Option<f32>,
     #[serde(alias = "dangerous_goods_storage_type")]
-    _dangerous_goods_storage_type: Option<String>,
+    dangerous_goods_storage_type: Option<String>,
     #[serde(alias = "eligible_for_inventory_discount")]
     _eligible_for_inventory_discount: Option<String>,
     #[serde(alias = "qualifies_for_inventory_discount")]
@@ -70,18 +101,135 @@ pub struct MonthlyStorageFees {
 }
 
 impl MonthlyStorageFees {
-    fn from_path<P>(path: P) -> Result<Vec<MonthlyStorageFees>, csv::Error>
-    where
-        P: AsRef<Path>,
-    {
-        let mut rdr = csv::Reader::from_path(path)?;
-        let msf = rdr
-            .records()
-            .filter_map(|x| x.ok())
-            .map(|x| x.deserialize(None))
-            .filter_map(|x| x.ok())
-            .collect::<Vec<MonthlyStorageFees>>();
-        Ok(msf)
+    /// Deserialize each row of an already sniffed report.
+    ///
+    /// Rows with a unit that is not known are rejected, see [`Self::normalize`].
+    /// Rows of a country code that is not a known marketplace are kept for
+    /// any marketplace, with a warning.
+    fn from_reader<R: Read>(
+        rdr: csv::Reader<R>,
+        file: &Path,
+        diagnostics: &mut Diagnostics,
+    ) -> Vec<Row<MonthlyStorageFees>> {
+        let rows: Vec<Row<MonthlyStorageFees>> = rows(rdr, file, diagnostics);
+        rows.into_iter()
+            .filter_map(|row| {
+                if let Err(err) = row.value.known_country() {
+                    let reason = format!("{err:#}, read as any marketplace");
+                    let err = anyhow!(ColumnError::new("country_code", reason));
+                    diagnostics.warn(file, Some(row.line), &err);
+                };
+                match row.value.normalize() {
+                    Ok(value) => Some(Row {
+                        line: row.line,
+                        value,
+                    }),
+                    Err(err) => {
+                        diagnostics.reject(file, Some(row.line), &err);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Convert the dimensions, weight, and volume of [`Self`] into inches,
+    /// pounds, and cubic feet.
+    ///
+    /// Empty unit columns are assumed to be in the units of the marketplace,
+    /// see [`Marketplace::length_unit`]. Rows without a marketplace are
+    /// assumed to already be in inches, pounds, and cubic feet.
+    ///
+    /// # Errors
+    ///
+    /// The month of charge, or any of the unit columns, are not known.
+    fn normalize(mut self) -> Result<Self> {
+        let column_err = |column| move |err| ColumnError::new(column, err);
+        let marketplace = self.marketplace();
+        self.month().map_err(column_err("month_of_charge"))?;
+        let (length, weight, volume) = match marketplace {
+            Some(x) => (x.length_unit(), x.weight_unit(), x.volume_unit()),
+            None => Default::default(),
+        };
+        let length =
+            parse_unit(&self.measurement_units, length).map_err(column_err("measurement_units"))?;
+        let weight = parse_unit(&self.weight_units, weight).map_err(column_err("weight_units"))?;
+        let volume = parse_unit(&self.volume_units, volume).map_err(column_err("volume_units"))?;
+
+        let to_inches = |x: f32| length.to_inches(x);
+        self.longest_side = self.longest_side.map(to_inches);
+        self.median_side = self.median_side.map(to_inches);
+        self.shortest_side = self.shortest_side.map(to_inches);
+        self.weight = self.weight.map(|x| weight.to_pounds(x));
+        let to_cubic_feet = |x: f32| volume.to_cubic_feet(x);
+        self.item_volume = self.item_volume.map(to_cubic_feet);
+        self.estimated_total_item_volume = self.estimated_total_item_volume.map(to_cubic_feet);
+        // The rate is charged per unit of volume, rather than being a volume.
+        self.storage_rate = self.storage_rate.map(|x| x / volume.to_cubic_feet(1.0));
+
+        self.measurement_units = Some(String::from("inches"));
+        self.weight_units = Some(String::from("pounds"));
+        self.volume_units = Some(String::from("cubic feet"));
+        Ok(self)
+    }
+
+    /// Returns the marketplace of the country code, `None` when there is no
+    /// country code or it is not known.
+    fn marketplace(&self) -> Option<Marketplace> {
+        self.known_country().ok().flatten()
+    }
+
+    /// # Errors
+    ///
+    /// The country code is not one of [`Marketplace::ALL`].
+    fn known_country(&self) -> Result<Option<Marketplace>> {
+        match self.country_code.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(code) => Ok(Some(code.parse()?)),
+        }
+    }
+
+    /// Returns the month of charge, if there is one.
+    fn month(&self) -> Result<Option<ReportMonth>> {
+        match self.month_of_charge.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(month) => Ok(Some(month.parse()?)),
+        }
+    }
+
+    /// Convert [`Self`] into a partial [`Product`], read from `source`.
+    ///
+    /// The month of charge has already been checked by [`Self::normalize`].
+    fn into_product(self, source: Source) -> Product {
+        let marketplace = self.marketplace();
+        let dated = self.month().ok().flatten();
+        let hazmat = self
+            .dangerous_goods_storage_type
+            .as_deref()
+            .and_then(|x| x.parse::<Hazmat>().ok());
+        let amz_dims = [
+            self.longest_side.unwrap_or_default(),
+            self.median_side.unwrap_or_default(),
+            self.shortest_side.unwrap_or_default(),
+        ];
+        let on_hand = self.average_quantity_on_hand.filter(|x| x > &0.0);
+        let unit_storage_fee = on_hand.and_then(|x| Some(self.estimated_monthly_storage_fee? / x));
+        Product {
+            fnsku: Some(self.fnsku),
+            asin: source.tag(self.asin),
+            title: source.tag(self.product_name),
+            amz_size: source.tag(self.product_size_tier),
+            pounds: source.tag(self.weight),
+            amz_dimensions: source.tag(Some(amz_dims)),
+            volume: source.tag(self.item_volume),
+            hazmat: source.tag(hazmat),
+            fulfillment_centers: self.fulfillment_center.into_iter().collect(),
+            storage_rate: self.storage_rate,
+            unit_storage_fee,
+            marketplace,
+            dated,
+            ..Default::default()
+        }
     }
 }
 
@@ -95,36 +243,50 @@ pub struct AmzFbaInventory {
     #[serde(alias = "asin")]
     _asin: String,
     #[serde(alias = "condition-type")]
-    condition: String,
+    condition: Option<Condition>,
     #[serde(alias = "Warehouse-Condition-code")]
-    _warehouse: String,
+    warehouse: String,
     #[serde(alias = "Quantity Available")]
-    _available: String,
+    available: Option<u32>,
 }
 
 impl AmzFbaInventory {
-    fn from_path<P>(path: P) -> anyhow::Result<Vec<AmzFbaInventory>>
-    where
-        P: AsRef<Path>,
-    {
-        let mut rdr = csv::Reader::from_path(path)?;
-        let header = rdr.headers()?;
-        // Stop other reports from deserde into this.
-        if header.into_iter().any(|x| x.eq_ignore_ascii_case("weight")) {
-            bail!("wrong report type")
-        };
-        let afi = rdr
-            .records()
-            .filter_map(|x| x.ok())
-            .map(|x| x.deserialize(None))
-            .filter_map(|x| x.ok())
-            .collect::<Vec<AmzFbaInventory>>();
-        Ok(afi)
+    /// Deserialize each row of an already sniffed report.
+    fn from_reader<R: Read>(
+        rdr: csv::Reader<R>,
+        file: &Path,
+        diagnostics: &mut Diagnostics,
+    ) -> Vec<Row<AmzFbaInventory>> {
+        rows(rdr, file, diagnostics)
+    }
+
+    /// Convert [`Self`] into a partial [`Product`], read from `source`.
+    fn into_product(self, source: Source) -> Product {
+        let available = self.sellable_units();
+        Product {
+            fnsku: Some(self.fnsku),
+            msku: source.tag(Some(self.msku)),
+            condition: source.tag(self.condition),
+            available: source.tag(available),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the available units, when they are in a sellable condition.
+    fn sellable_units(&self) -> Option<u32> {
+        let sellable = self.warehouse.trim().eq_ignore_ascii_case("SELLABLE");
+        self.available.filter(|_| sellable)
     }
 }
 
 /// Add item information to [`Entry`]s, by mutating in place.
 ///
+/// Item information is looked up within the [`Catalog`] of every report in
+/// `.local`, only reports that changed since the last call are read again.
+/// Only report rows of `marketplace` are used, see [`Catalog::load_for`].
+/// The returned [`EnrichmentReport`] lists where reports disagreed, while any
+/// report or row that could not be read is added to `diagnostics`.
+///
 /// See the following for more information:
 /// * [`AmzFbaInventory`]
 /// * [`MonthlyStorageFees`]
@@ -133,62 +295,14 @@ impl AmzFbaInventory {
 /// [`AllListingsReport`]:(all_listings_report::AllListingsReport)
 /// [`AmzFbaInventory`]:(AmzFbaInventory)
 /// [`MonthlyStorageFees`]:(MonthlyStorageFees)
-fn fill_entries(entries: &mut Vec<Entry>) -> Result<(), Error> {
-    let afi_vec = std::fs::read_dir(".local")?
-        .filter_map(|x| x.ok())
-        .filter_map(|x| AmzFbaInventory::from_path(x.path()).ok())
-        .flatten()
-        .collect::<Vec<_>>();
-
-    let msf_vec = std::fs::read_dir(".local")?
-        .filter_map(|x| x.ok())
-        .filter_map(|x| MonthlyStorageFees::from_path(x.path()).ok())
-        .flatten()
-        .collect::<Vec<_>>();
-
-    let alr_vec = std::fs::read_dir(".local")?
-        .filter_map(|x| x.ok())
-        .filter_map(|x| AllListingsReport::from_path(x.path()).ok())
-        .flatten()
-        .collect::<Vec<_>>();
-
-    for item in entries {
-        // MonthlyStorageFees pulling.
-        if let Some(found) = msf_vec.iter().find(|row| row.fnsku == item.get_fnsku()) {
-            item.set_title(found.product_name.clone());
-            item.set_amz_size(found.product_size_tier.clone());
-            item.set_asin(found.asin.clone());
-            item.set_total_pounds(found.weight);
-            item.set_amz_size(found.product_size_tier.clone());
-
-            let amz_dims = [
-                found.longest_side.unwrap_or_default(),
-                found.median_side.unwrap_or_default(),
-                found.shortest_side.unwrap_or_default(),
-            ];
-            // entry type has a separate field for input dims.
-            // doing this does not overwrite them.
-            item.set_amz_dimensions(Some(amz_dims));
-        };
-        // AmzFbaInventory pulling.
-        if let Some(found) = afi_vec.iter().find(|row| row.fnsku == item.get_fnsku()) {
-            let msku = found.msku.clone();
-            let condition = found.condition.clone();
-            item.set_msku(Some(msku));
-            item.set_condition(Some(condition));
-        };
-
-        if let Some(found) = alr_vec
-            .iter()
-            .find(|row| &row.seller_sku == item.get_msku())
-        {
-            let title = found.item_name.clone();
-            let upc = found.product_id.clone();
-            item.set_title(title);
-            item.set_upc(upc);
-        };
-    }
-    Ok(())
+fn fill_entries(
+    entries: &mut [Entry],
+    marketplace: Option<Marketplace>,
+    diagnostics: &mut Diagnostics,
+) -> Result<EnrichmentReport, Error> {
+    let catalog = Catalog::load_for(".local", marketplace)?;
+    diagnostics.extend(catalog.diagnostics().clone());
+    Ok(catalog.enrich_all(entries))
 }
 
 /// See [`GDrivePlan`].
@@ -224,6 +338,67 @@ struct GDriveEntry {
     _total_cases: Option<u32>,
     #[serde(alias = "Readable")]
     _readable: Option<String>,
+    #[serde(alias = "Box ID")]
+    box_id: Option<String>,
+}
+
+impl GDriveEntry {
+    /// Returns the case dimensions, when all three are greater than zero.
+    fn case_dimensions(&self) -> Option<[f32; 3]> {
+        let length = self.case_length.unwrap_or_default();
+        let width = self.case_width.unwrap_or_default();
+        let height = self.case_height.unwrap_or_default();
+        let dims = [length, width, height];
+        dims.iter().all(|x| x > &0.0).then_some(dims)
+    }
+}
+
+/// The dimensions and weight shared by every row of an explicit box.
+///
+/// See [`GDrivePlan::boxes_into_entries`].
+#[derive(Debug)]
+struct PackedBox {
+    /// The case id of the box, its "Box ID" made unique to the import.
+    id: String,
+    dims: Option<[f32; 3]>,
+    weight: Option<f32>,
+}
+
+impl PackedBox {
+    fn new(box_id: &str) -> Self {
+        Self {
+            id: format!("{box_id}-{}", gen_pw_uuid()),
+            dims: None,
+            weight: None,
+        }
+    }
+
+    /// Take the dimensions and weight of `value`, where it has them.
+    ///
+    /// # Errors
+    ///
+    /// The dimensions or weight differ from an earlier row of the box.
+    fn absorb(&mut self, box_id: &str, value: &GDriveEntry) -> Result<()> {
+        if let Some(dims) = value.case_dimensions() {
+            match self.dims {
+                Some(known) if known != dims => {
+                    let reason = format!("Box {box_id} was already given dimensions {known:?}");
+                    bail!(ColumnError::new("Case Length", reason));
+                }
+                _ => self.dims = Some(dims),
+            };
+        };
+        if let Some(weight) = value.case_weight {
+            match self.weight {
+                Some(known) if known != weight => {
+                    let reason = format!("Box {box_id} was already given a weight of {known}");
+                    bail!(ColumnError::new("Case Weight", reason));
+                }
+                _ => self.weight = Some(weight),
+            };
+        };
+        Ok(())
+    }
 }
 
 /// A batch of entries held withing a Google Drive sheet.
@@ -234,54 +409,257 @@ struct GDriveEntry {
 ///
 #[derive(Default, Debug)]
 pub struct GDrivePlan {
-    helper: Vec<GDriveEntry>,
+    file: PathBuf,
+    helper: Vec<Row<GDriveEntry>>,
+}
+
+/// The result of importing a plan.
+#[derive(Default, Debug)]
+pub struct Import {
+    pub entries: Vec<Entry>,
+    /// The marketplace the entries were enriched for, `None` for any.
+    pub marketplace: Option<Marketplace>,
+    pub enrichment: EnrichmentReport,
+    /// Every row, of the plan or of a report, that was not imported.
+    pub diagnostics: Diagnostics,
 }
 
 impl GDrivePlan {
-    pub fn proc_from_path<P>(path: P) -> Result<Vec<Entry>>
+    /// Read a plan from the file at `path`, enriching it with report data.
+    ///
+    /// Columns are read by the default [`MappingProfile`], and entries are
+    /// enriched from every marketplace, see [`Self::proc_with_profile`].
+    ///
+    /// # Errors
+    ///
+    /// * The supplied path is invalid.
+    /// * The reports used for enrichment cannot be read.
+    pub fn proc_from_path<P>(path: P) -> Result<Import>
     where
         P: AsRef<Path>,
     {
-        let mut rdr = csv::Reader::from_path(path)?;
-        let ok_recs = rdr.records().filter_map(|rec| rec.ok());
-        let de_recs = ok_recs.filter_map(|unw| unw.deserialize(None).ok());
-        let good_recs = de_recs
-            .filter(|gdp: &GDriveEntry| gdp.fnsku.is_some())
-            .collect::<Vec<GDriveEntry>>();
-        let gdp = GDrivePlan { helper: good_recs };
-        Vec::<Entry>::try_from(gdp)
+        GDrivePlan::proc_with_profile(path, &MappingProfile::default(), None)
     }
 
-    fn _from_path<P>(path: P) -> Self
+    /// Read a plan from the file at `path`, with columns named by `profile`.
+    ///
+    /// Entries are enriched from the report rows of `marketplace`.
+    ///
+    /// Workbooks are read from their first sheet, see [`is_workbook`], any
+    /// other file is read as csv. Gzip files are decompressed, and the entries
+    /// of every csv within a zip archive are imported together. Rows that
+    /// cannot be imported are listed in [`Import::diagnostics`].
+    ///
+    /// # Errors
+    ///
+    /// * The supplied path is invalid.
+    /// * The file is compressed, but cannot be decompressed.
+    /// * The reports used for enrichment cannot be read.
+    pub fn proc_with_profile<P>(
+        path: P,
+        profile: &MappingProfile,
+        marketplace: Option<Marketplace>,
+    ) -> Result<Import>
     where
         P: AsRef<Path>,
     {
-        let mut rdr = csv::Reader::from_path(path).unwrap();
-        let ok_recs = rdr.records().filter_map(|rec| rec.ok());
-        let de_recs = ok_recs.filter_map(|unw| unw.deserialize(None).ok());
-        let good_recs = de_recs
-            .filter(|gdp: &GDriveEntry| gdp.fnsku.is_some())
-            .collect::<Vec<GDriveEntry>>();
-        GDrivePlan { helper: good_recs }
+        let path = path.as_ref();
+        if is_workbook(path) {
+            let first = sheet_names(path)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("{} has no sheets", path.display()))?;
+            return GDrivePlan::proc_from_sheet(path, &first, profile, marketplace);
+        };
+        let mut diagnostics = Diagnostics::default();
+        let plans = archive::members(path)?
+            .into_iter()
+            .map(|member| {
+                let rdr = csv::Reader::from_reader(member.bytes.as_slice());
+                GDrivePlan::from_reader(rdr, member.file, profile, &mut diagnostics)
+            })
+            .collect();
+        GDrivePlan::proc(plans, marketplace, diagnostics)
     }
-}
 
-impl TryFrom<GDrivePlan> for Vec<Entry> {
-    type Error = anyhow::Error;
+    /// Read a plan from `sheet` of the workbook at `path`.
+    ///
+    /// Columns are named by the first row of the sheet, exactly as they are
+    /// for a csv plan. See [`Self::proc_with_profile`].
+    ///
+    /// # Errors
+    ///
+    /// * The workbook or sheet cannot be read.
+    /// * The reports used for enrichment cannot be read.
+    pub fn proc_from_sheet<P>(
+        path: P,
+        sheet: &str,
+        profile: &MappingProfile,
+        marketplace: Option<Marketplace>,
+    ) -> Result<Import>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let bytes = workbook::sheet_as_csv(path, sheet)?;
+        let file = PathBuf::from(format!("{}[{sheet}]", path.display()));
+        let mut diagnostics = Diagnostics::default();
+        let rdr = csv::Reader::from_reader(bytes.as_slice());
+        let gdp = GDrivePlan::from_reader(rdr, file, profile, &mut diagnostics);
+        GDrivePlan::proc(vec![gdp], marketplace, diagnostics)
+    }
 
-    fn try_from(value: GDrivePlan) -> std::result::Result<Self, Self::Error> {
-        // weird parent / child trailt rules.
-        let value0 = value.helper;
-        let mut conversions = value0
-            .into_iter()
-            .filter_map(|x| Vec::<Entry>::try_from(x).ok())
-            .flatten()
-            .collect::<Vec<_>>();
-        match fill_entries(&mut conversions) {
-            Ok(_) => dbg!("no issues"),
-            Err(_) => dbg!("issues"),
+    /// Convert and enrich the entries of each of the `plans`, for `marketplace`.
+    fn proc(
+        plans: Vec<GDrivePlan>,
+        marketplace: Option<Marketplace>,
+        mut diagnostics: Diagnostics,
+    ) -> Result<Import> {
+        let mut entries = vec![];
+        for gdp in plans {
+            entries.extend(gdp.into_entries(&mut diagnostics));
+        }
+        let enrichment = fill_entries(&mut entries, marketplace, &mut diagnostics)?;
+        Ok(Import {
+            entries,
+            marketplace,
+            enrichment,
+            diagnostics,
+        })
+    }
+
+    /// Read the rows of a csv plan, `file` names it within diagnostics.
+    ///
+    /// The headers are renamed by `profile`, and its loose pack types are
+    /// read as "Loose". When a [`REQUIRED`] column is missing, it is rejected
+    /// and no rows are read. Rows without an Fnsku are skipped, unless they
+    /// also have a quantity.
+    fn from_reader<R: Read>(
+        mut rdr: csv::Reader<R>,
+        file: PathBuf,
+        profile: &MappingProfile,
+        diagnostics: &mut Diagnostics,
+    ) -> Self {
+        let headers = rdr.headers().cloned().unwrap_or_default();
+        let missing = profile.unmapped_required(&headers);
+        if !missing.is_empty() {
+            for column in missing {
+                let reason = format!("Not mapped by the '{}' profile", profile.name);
+                let err = anyhow!(ColumnError::new(column, reason));
+                diagnostics.reject(&file, None, &err);
+            }
+            return GDrivePlan {
+                file,
+                helper: vec![],
+            };
         };
-        Ok(conversions)
+        rdr.set_headers(profile.map_headers(&headers));
+
+        let helper = rows(rdr, &file, diagnostics)
+            .into_iter()
+            .filter(|row: &Row<GDriveEntry>| {
+                let value = &row.value;
+                if value.fnsku.is_none() && value.quantity.is_some() {
+                    let err = anyhow!(ColumnError::new("FNSKU", "Expected an Fnsku"));
+                    diagnostics.reject(&file, Some(row.line), &err);
+                };
+                value.fnsku.is_some()
+            })
+            .map(|mut row| {
+                if row
+                    .value
+                    .pack_type
+                    .as_deref()
+                    .is_some_and(|x| profile.is_loose(x))
+                {
+                    row.value.pack_type = Some(String::from("Loose"));
+                };
+                row
+            })
+            .collect();
+        GDrivePlan { file, helper }
+    }
+
+    /// Convert each row of [`Self`] into [`Entry`]s.
+    ///
+    /// Rows with a "Box ID" are converted by [`Self::boxes_into_entries`].
+    /// Rows that fail to convert are recorded within `diagnostics`.
+    fn into_entries(self, diagnostics: &mut Diagnostics) -> Vec<Entry> {
+        let (boxed, unboxed): (Vec<_>, Vec<_>) = self
+            .helper
+            .into_iter()
+            .partition(|row| row.value.box_id.is_some());
+
+        let mut entries = vec![];
+        for row in unboxed {
+            match Vec::<Entry>::try_from(row.value) {
+                Ok(converted) => entries.extend(converted),
+                Err(err) => diagnostics.reject(&self.file, Some(row.line), &err),
+            };
+        }
+        entries.extend(Self::boxes_into_entries(boxed, &self.file, diagnostics));
+        entries
+    }
+
+    /// Convert rows with an explicit "Box ID" into [`Entry`]s.
+    ///
+    /// Every row sharing a box id is packed into the same case, so a box may
+    /// hold several Fnskus. Each row puts its full quantity into the box, a
+    /// "Case QT" is taken as the size of the inner packs and must divide it.
+    /// The case dimensions and weight describe the whole box, they only need
+    /// to be given on one of its rows. Box ids are only unique to a plan, so
+    /// each case id is the box id followed by a uuid.
+    fn boxes_into_entries(
+        rows: Vec<Row<GDriveEntry>>,
+        file: &Path,
+        diagnostics: &mut Diagnostics,
+    ) -> Vec<Entry> {
+        let mut boxes: HashMap<String, PackedBox> = HashMap::new();
+        let mut accepted = vec![];
+        for row in rows {
+            let box_id = row.value.box_id.clone().unwrap_or_default();
+            let packed_box = boxes
+                .entry(box_id.clone())
+                .or_insert_with(|| PackedBox::new(&box_id));
+            match packed_box.absorb(&box_id, &row.value) {
+                Ok(_) => accepted.push((box_id, row)),
+                Err(err) => diagnostics.reject(file, Some(row.line), &err),
+            };
+        }
+
+        let mut entries = vec![];
+        for (box_id, row) in accepted {
+            let packed_box = &boxes[&box_id];
+            let into_entry = || {
+                let value = &row.value;
+                let fnsku = value
+                    .fnsku
+                    .clone()
+                    .ok_or_else(|| ColumnError::new("FNSKU", "Expected an Fnsku"))?;
+                let units = value
+                    .quantity
+                    .filter(|x| x > &0)
+                    .ok_or_else(|| ColumnError::new("Quantity", "Expected a quantity"))?;
+                if let Some(per_pack) = value.case_qt {
+                    if !matches!(units.checked_rem(per_pack), Some(0)) {
+                        let reason = format!("{units} is not divisible by a Case QT of {per_pack}");
+                        bail!(ColumnError::new("Quantity", reason));
+                    };
+                };
+                let mut entry = Entry::default();
+                entry.set_fnsku(fnsku);
+                entry.set_units(units as i32);
+                entry.set_id(packed_box.id.clone());
+                entry.set_dimensions(packed_box.dims);
+                entry.set_total_pounds(packed_box.weight);
+                Ok(entry)
+            };
+            match into_entry() {
+                Ok(entry) => entries.push(entry),
+                Err(err) => diagnostics.reject(file, Some(row.line), &err),
+            };
+        }
+        entries
     }
 }
 
@@ -292,10 +670,10 @@ impl TryFrom<GDriveEntry> for Vec<Entry> {
         let fnsku = value
             .fnsku
             .clone()
-            .ok_or(anyhow!("Expected Fnsku in {value:#?}."))?;
+            .ok_or_else(|| ColumnError::new("FNSKU", "Expected an Fnsku"))?;
         let units = value
             .quantity
-            .ok_or(anyhow!("Expected Fnsku in {value:#?}."))?;
+            .ok_or_else(|| ColumnError::new("Quantity", "Expected a quantity"))?;
 
         let mut helper = vec![];
         match &value.pack_type {
@@ -308,29 +686,22 @@ impl TryFrom<GDriveEntry> for Vec<Entry> {
                 entry.set_units(units as i32);
                 entry.set_total_pounds(Some(total_pounds));
                 entry.set_id(id);
+                entry.set_loose(true);
                 helper.push(entry);
             }
             _ => {
                 let per_case = value
                     .case_qt
-                    .ok_or(anyhow!("Expect 'Case Qt' in {value:#?}."))?;
+                    .ok_or_else(|| ColumnError::new("Case QT", "Expected a case quantity"))?;
                 if !matches!(units.checked_rem(per_case), Some(0)) {
-                    bail!("Expect 'Total Qt' to be evenly divisible by 'Case Qt' in {value:#?}.");
-                };
-                let length = value.case_length.unwrap_or_default();
-                let width = value.case_length.unwrap_or_default();
-                let height = value.case_length.unwrap_or_default();
-                let dims = [length, width, height];
-
-                let check_dims = if dims.iter().all(|x| x > &0.0) {
-                    Some(dims)
-                } else {
-                    None
+                    let reason = format!("{units} is not divisible by a Case QT of {per_case}");
+                    bail!(ColumnError::new("Quantity", reason));
                 };
+                let check_dims = value.case_dimensions();
 
                 let cases = units.checked_div(per_case).unwrap_or_default();
                 if cases.eq(&0) {
-                    bail!("Expected {value:#?} to not be zero cases.");
+                    bail!(ColumnError::new("Quantity", "Expected at least one case"));
                 };
                 let case_weight = value.case_weight;
                 for _ in 0..cases {
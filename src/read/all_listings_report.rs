@@ -1,12 +1,10 @@
-use std::u8;
-use csv::StringRecord;
 use super::*;
+use crate::plan::condition::Condition;
 
 /// Representation of the All Listings Report (Custom).
 ///
 /// Compatible with the standard report of the same name.
 #[derive(Default, Debug, serde::Deserialize)]
-#[serde(deny_unknown_fields)]
 #[allow(dead_code)]
 pub(super) struct AllListingsReport {
     #[serde(rename(deserialize = "seller-sku"))]
@@ -18,73 +16,29 @@ pub(super) struct AllListingsReport {
     #[serde(rename(deserialize = "product-id-type"))]
     pub(super) product_id_type: Option<String>,
     #[serde(rename(deserialize = "item-condition"))]
-    #[serde(deserialize_with = "translate_condition")]
-    pub(super) item_condition: Condition,
+    pub(super) item_condition: Option<Condition>,
     #[serde(rename(deserialize = "product-id"))]
     pub(super) product_id: Option<String>,
 }
 impl AllListingsReport {
-    /// Attempt to deserialize from a path.
-    ///
-    /// # Errors
-    ///
-    /// * The supplied path is invalid.
-    /// * Deserialization fails.
-    pub fn from_path<P>(path: P) -> anyhow::Result<Vec<AllListingsReport>>
-    where
-        P: AsRef<Path>,
-    {
-        // Set the delimiter to a tab char.
-        let mut rdr = csv::ReaderBuilder::new().delimiter(b'\t').from_path(path)?;
-        let expect_hdr = StringRecord::from(vec![
-            "seller-sku",
-            "asin1",
-            "item-name",
-            "product-id-type",
-            "item-condition",
-            "product-id",
-        ]);
-
-        let hdr = rdr.headers()? ;
-        if  hdr != &expect_hdr {
-            return Err(anyhow!("Expected{expect_hdr:#?}, got {hdr:#?}"));
-        };
-        let alr = rdr
-            .records()
-            .filter_map(|x| x.ok())
-            .map(|x| x.deserialize(None))
-            .filter_map(|x| x.ok())
-            .collect::<Vec<AllListingsReport>>();
-        Ok(alr)
+    /// Deserialize each row of an already sniffed, tab delimited report.
+    pub(super) fn from_reader<R: Read>(
+        rdr: csv::Reader<R>,
+        file: &Path,
+        diagnostics: &mut Diagnostics,
+    ) -> Vec<Row<AllListingsReport>> {
+        rows(rdr, file, diagnostics)
     }
-}
-
-/// Product conditions for an Asin.
-#[derive(PartialEq, Default, Debug)]
-pub(super) enum Condition {
-    #[default]
-    New,
-    UsedLikeNew,
-    None,
-}
 
-// Custom deserialization for Amz's coding of item conditions.
-//
-// * `11`: [`Condition::New`].
-// * `1`: [`Condition::UsedLikeNew`].
-//
-// Other variants exist within the reports but these are the ones we
-// tend to care about.
-fn translate_condition<'de, D>(deserializer: D) -> Result<Condition, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let c = u32::deserialize(deserializer)?;
-    if c == 11 {
-        Ok(Condition::New)
-    } else if c == 1 {
-        Ok(Condition::UsedLikeNew)
-    } else {
-        Ok(Condition::None)
+    /// Convert [`Self`] into a partial [`Product`], read from `source`.
+    pub(super) fn into_product(self, source: Source) -> Product {
+        Product {
+            msku: source.tag(self.seller_sku),
+            asin: source.tag(self.asin),
+            title: source.tag(self.item_name),
+            upc: source.tag(self.product_id),
+            condition: source.tag(self.item_condition),
+            ..Default::default()
+        }
     }
 }
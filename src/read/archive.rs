@@ -0,0 +1,143 @@
+use super::workbook::{self, is_workbook};
+use anyhow::{anyhow, Context, Result};
+use flate2::read::MultiGzDecoder;
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+/// The first bytes of every gzip file.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The most bytes that a single file is decompressed to.
+const MAX_DECOMPRESSED: u64 = 512 * 1024 * 1024;
+
+/// File extensions of zip members that can hold a report or a plan, members
+/// without an extension are read as well.
+const MEMBER_EXTENSIONS: [&str; 4] = ["csv", "tsv", "txt", "gz"];
+
+/// A single file read from disk, decompressed when it was compressed.
+#[derive(Debug)]
+pub(super) struct Member {
+    /// Names the member within diagnostics and sources.
+    ///
+    /// Members of a zip archive are named `archive.zip[member.csv]`, every
+    /// other file keeps its own path.
+    pub(super) file: PathBuf,
+    pub(super) bytes: Vec<u8>,
+}
+
+/// Returns true if the file at `path` is read as a zip archive.
+///
+/// This only looks at the file extension, as workbooks are zip archives too.
+fn is_zip<P>(path: P) -> bool
+where
+    P: AsRef<Path>,
+{
+    let ext = path.as_ref().extension().and_then(|x| x.to_str());
+    ext.is_some_and(|x| x.eq_ignore_ascii_case("zip"))
+}
+
+/// Read the file at `path`, along with every file that it holds.
+///
+/// Zip archives return each of their files, skipping directories and
+/// members that cannot hold a report, see [`is_junk`]. Gzip files are
+/// recognized by their contents and are decompressed, any other file is
+/// returned as is.
+///
+/// # Errors
+///
+/// * The file cannot be read.
+/// * The file is not a valid zip archive or gzip file.
+/// * A file decompresses to more than [`MAX_DECOMPRESSED`] bytes.
+/// * A workbook within a zip archive cannot be read.
+pub(super) fn members<P>(path: P) -> Result<Vec<Member>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let context = || format!("Reading {}", path.display());
+    if is_zip(path) {
+        return zip_members(path).with_context(context);
+    };
+    let bytes = std::fs::read(path).with_context(context)?;
+    let bytes = match bytes.starts_with(&GZIP_MAGIC) {
+        true => gunzip(&bytes).with_context(context)?,
+        false => bytes,
+    };
+    let file = path.to_path_buf();
+    Ok(vec![Member { file, bytes }])
+}
+
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    read_capped(MultiGzDecoder::new(bytes))
+}
+
+/// Read all of `rdr`, failing rather than reading past [`MAX_DECOMPRESSED`].
+fn read_capped<R: Read>(rdr: R) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    rdr.take(MAX_DECOMPRESSED + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_DECOMPRESSED {
+        let mib = MAX_DECOMPRESSED / 1024 / 1024;
+        return Err(anyhow!("Decompresses to more than {mib} MiB"));
+    };
+    Ok(bytes)
+}
+
+/// Returns true if the zip member `name` cannot hold a report or a plan.
+///
+/// This skips hidden files, the resource forks that macOS adds under
+/// `__MACOSX`, readme files, and files of any other extension.
+fn is_junk(name: &str) -> bool {
+    let path = Path::new(name);
+    let hidden = path.components().any(|x| {
+        let x = x.as_os_str().to_string_lossy();
+        x.starts_with('.') || x == "__MACOSX"
+    });
+    let stem = path
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_ascii_lowercase());
+    let readme = stem.is_some_and(|x| x.starts_with("readme"));
+    let ext = path.extension().and_then(|x| x.to_str());
+    let known = ext.is_none_or(|x| MEMBER_EXTENSIONS.iter().any(|y| y.eq_ignore_ascii_case(x)));
+    hidden || readme || !(known || is_workbook(path))
+}
+
+/// Returns true if the file at `path` may hold a report, see [`members`].
+///
+/// Like [`is_junk`] this only looks at the file name, so that other files,
+/// such as ledger transactions, are skipped before they are read.
+pub(crate) fn may_hold_report<P>(path: P) -> bool
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let name = path.file_name().map(|x| x.to_string_lossy());
+    is_zip(path) || name.is_some_and(|x| !is_junk(&x))
+}
+
+/// Members that are themselves gzipped are decompressed as well, and the
+/// first sheet of each workbook is read as csv.
+fn zip_members(path: &Path) -> Result<Vec<Member>> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut members = vec![];
+    for idx in 0..archive.len() {
+        let zipped = archive.by_index(idx)?;
+        if zipped.is_dir() || is_junk(zipped.name()) {
+            continue;
+        };
+        let name = zipped.name().to_string();
+        let file = PathBuf::from(format!("{}[{name}]", path.display()));
+        let context = || format!("Reading {name}");
+        let mut bytes = read_capped(zipped).with_context(context)?;
+        if bytes.starts_with(&GZIP_MAGIC) {
+            bytes = gunzip(&bytes).with_context(context)?;
+        };
+        if is_workbook(&name) {
+            bytes = workbook::first_sheet_as_csv(&bytes).with_context(context)?;
+        };
+        members.push(Member { file, bytes });
+    }
+    Ok(members)
+}
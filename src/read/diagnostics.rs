@@ -0,0 +1,165 @@
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, io::Write, path::Path};
+
+/// An error that is caused by the value of a single column.
+///
+/// Return this from within an [`anyhow::Error`] so that a [`Rejection`]
+/// can point at the offending column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnError {
+    pub column: String,
+    pub reason: String,
+}
+
+impl ColumnError {
+    pub fn new<C, R>(column: C, reason: R) -> Self
+    where
+        C: ToString,
+        R: ToString,
+    {
+        Self {
+            column: column.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl Display for ColumnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.column, self.reason)
+    }
+}
+
+impl std::error::Error for ColumnError {}
+
+/// A single row that was not imported, or only imported in part, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rejection {
+    pub file: String,
+    pub line: Option<u64>,
+    pub column: Option<String>,
+    pub reason: String,
+}
+
+impl Rejection {
+    /// When `err` is a [`ColumnError`] the column is recorded as well.
+    fn new<P>(file: P, line: Option<u64>, err: &anyhow::Error) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let (column, reason) = match err.downcast_ref::<ColumnError>() {
+            Some(x) => (Some(x.column.clone()), x.reason.clone()),
+            None => (None, format!("{err:#}")),
+        };
+        Self {
+            file: file.as_ref().display().to_string(),
+            line,
+            column,
+            reason,
+        }
+    }
+}
+
+/// Every [`Rejection`] found during an import.
+///
+/// Rows that were still imported are kept apart, see [`Diagnostics::warn`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostics {
+    rejected: Vec<Rejection>,
+    #[serde(default)]
+    warnings: Vec<Rejection>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, rejection: Rejection) {
+        self.rejected.push(rejection);
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.rejected.extend(other.rejected);
+        self.warnings.extend(other.warnings);
+    }
+
+    /// Returns every row that was imported despite a problem.
+    pub fn warnings(&self) -> impl Iterator<Item = &Rejection> {
+        self.warnings.iter()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rejection> {
+        self.rejected.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rejected.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rejected.is_empty()
+    }
+
+    /// Reject the row at `line` of `file` because of `err`.
+    ///
+    /// When `err` is a [`ColumnError`] the column is recorded as well.
+    pub fn reject<P>(&mut self, file: P, line: Option<u64>, err: &anyhow::Error)
+    where
+        P: AsRef<Path>,
+    {
+        self.push(Rejection::new(file, line, err));
+    }
+
+    /// Note a problem with the row at `line` of `file`, which was imported.
+    ///
+    /// See [`Self::reject`].
+    pub fn warn<P>(&mut self, file: P, line: Option<u64>, err: &anyhow::Error)
+    where
+        P: AsRef<Path>,
+    {
+        self.warnings.push(Rejection::new(file, line, err));
+    }
+
+    /// Reject a row of `file` because reading or deserializing it failed.
+    ///
+    /// `headers` is used to name the column that failed to deserialize.
+    pub fn reject_csv<P>(&mut self, file: P, headers: Option<&StringRecord>, err: &csv::Error)
+    where
+        P: AsRef<Path>,
+    {
+        let line = err.position().map(|x| x.line());
+        let column = match err.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => err
+                .field()
+                .and_then(|idx| headers?.get(idx as usize))
+                .map(String::from),
+            _ => None,
+        };
+        let reason = match err.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => err.kind().to_string(),
+            _ => err.to_string(),
+        };
+        self.push(Rejection {
+            file: file.as_ref().display().to_string(),
+            line,
+            column,
+            reason,
+        });
+    }
+
+    /// Write each [`Rejection`] and warning as a row of csv, with a header row.
+    ///
+    /// The `kind` column tells the rows that were rejected from the rows that
+    /// were imported with a warning.
+    pub fn write_csv<W: Write>(&self, wtr: W) -> anyhow::Result<()> {
+        let mut wtr = csv::Writer::from_writer(wtr);
+        wtr.write_record(["kind", "file", "line", "column", "reason"])?;
+        let rejected = self.iter().map(|x| ("rejected", x));
+        let warnings = self.warnings().map(|x| ("warning", x));
+        for (kind, row) in rejected.chain(warnings) {
+            let line = row.line.map(|x| x.to_string()).unwrap_or_default();
+            let column = row.column.as_deref().unwrap_or_default();
+            wtr.write_record([kind, &row.file, &line, column, &row.reason])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+/// The month that a dated report row describes.
+///
+/// Orders from oldest to most recent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ReportMonth {
+    year: u16,
+    month: u8,
+}
+
+impl ReportMonth {
+    /// # Errors
+    ///
+    /// `month` is not `1` through `12`.
+    pub fn new(year: u16, month: u8) -> Result<Self, Error> {
+        if !(1..=12).contains(&month) {
+            bail!("Unknown month '{month}'");
+        };
+        Ok(Self { year, month })
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+}
+
+impl Display for ReportMonth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+/// Parses the `YYYY-MM` of Amazon reports, any day that follows is ignored.
+impl FromStr for ReportMonth {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split(['-', '/']);
+        let year = parts.next().filter(|x| x.len() == 4);
+        let month = parts.next().filter(|x| (1..=2).contains(&x.len()));
+        let parsed = year
+            .zip(month)
+            .and_then(|(y, m)| Some((y.parse().ok()?, m.parse().ok()?)));
+        match parsed {
+            Some((year, month)) => ReportMonth::new(year, month),
+            None => bail!("Unknown month of charge '{s}'"),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use super::Diagnostics;
+use anyhow::{bail, Result};
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// Every column understood by a Google Drive plan.
+pub const COLUMNS: [&str; 16] = [
+    "Info",
+    "FNSKU",
+    "Quantity",
+    "Pack Type",
+    "Staging Group",
+    "Unit Weight",
+    "Case QT",
+    "Print Order",
+    "Case Length",
+    "Case Width",
+    "Case Height",
+    "Case Weight",
+    "Total Weight",
+    "Total Cases",
+    "Readable",
+    "Box ID",
+];
+
+/// Columns which a plan cannot be imported without.
+pub const REQUIRED: [&str; 2] = ["FNSKU", "Quantity"];
+
+/// A saved mapping from the columns of a sheet to the columns of a plan.
+///
+/// Columns that are not mapped are still read when they are named like
+/// one of [`COLUMNS`], ignoring case.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappingProfile {
+    pub name: String,
+    /// Sheet column to plan column, see [`COLUMNS`].
+    #[serde(default)]
+    pub columns: BTreeMap<String, String>,
+    /// Values of "Pack Type" that mean the units are loose.
+    #[serde(default = "default_loose")]
+    pub loose: Vec<String>,
+}
+
+fn default_loose() -> Vec<String> {
+    vec![String::from("Loose")]
+}
+
+impl Default for MappingProfile {
+    fn default() -> Self {
+        Self {
+            name: String::from("Default"),
+            columns: BTreeMap::default(),
+            loose: default_loose(),
+        }
+    }
+}
+
+impl MappingProfile {
+    /// Read every profile saved within `dir`, sorted by name.
+    ///
+    /// A missing `dir` has no profiles. A profile that cannot be read, or is
+    /// invalid, is skipped and recorded within `diagnostics`.
+    pub fn load_all<P>(dir: P, diagnostics: &mut Diagnostics) -> Vec<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let Ok(read_dir) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+        let mut profiles = vec![];
+        for path in read_dir.filter_map(|x| Some(x.ok()?.path())) {
+            if path.extension().is_none_or(|x| x != "json") {
+                continue;
+            };
+            match Self::read(&path) {
+                Ok(profile) => profiles.push(profile),
+                Err(err) => diagnostics.reject(&path, None, &err),
+            };
+        }
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles
+    }
+
+    /// Read and validate the profile saved at `path`.
+    fn read(path: &Path) -> Result<Self> {
+        let profile: MappingProfile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Write [`Self`] to `dir`, replacing a profile of the same name.
+    ///
+    /// # Errors
+    ///
+    /// [`Self`] is invalid, or the underlying file system fails.
+    pub fn save<P>(&self, dir: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.validate()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.as_ref().join(format!("{}.json", self.name));
+        let json = serde_json::to_string_pretty(self)?;
+        Ok(std::fs::write(path, json)?)
+    }
+
+    /// # Errors
+    ///
+    /// * The name is empty, or cannot be used as a file name.
+    /// * A column is mapped to something other than one of [`COLUMNS`].
+    pub fn validate(&self) -> Result<()> {
+        let name = self.name.trim();
+        if name.is_empty() || name.contains(['/', '\\', '.']) {
+            bail!("'{}' is not a valid profile name", self.name);
+        };
+        for (from, to) in self.columns.iter() {
+            if !COLUMNS.contains(&to.as_str()) {
+                bail!("Profile '{name}' maps '{from}' to the unknown column '{to}'");
+            };
+        }
+        Ok(())
+    }
+
+    /// Returns true if `pack_type` is one of the loose pack types.
+    pub fn is_loose(&self, pack_type: &str) -> bool {
+        let pack_type = pack_type.trim();
+        self.loose.iter().any(|x| x.eq_ignore_ascii_case(pack_type))
+    }
+
+    /// Rename each of the `headers` to the plan column it is mapped to.
+    ///
+    /// Headers that are not mapped, nor named like a plan column, are kept.
+    pub fn map_headers(&self, headers: &StringRecord) -> StringRecord {
+        headers
+            .iter()
+            .map(|header| {
+                let header = header.trim();
+                let mapped = self
+                    .columns
+                    .iter()
+                    .find(|(from, _)| from.trim().eq_ignore_ascii_case(header))
+                    .map(|(_, to)| to.as_str());
+                let known = COLUMNS.iter().find(|x| x.eq_ignore_ascii_case(header));
+                mapped.or(known.copied()).unwrap_or(header)
+            })
+            .collect()
+    }
+
+    /// Returns the [`REQUIRED`] columns missing from the mapped `headers`.
+    pub fn unmapped_required(&self, headers: &StringRecord) -> Vec<&'static str> {
+        let mapped = self.map_headers(headers);
+        REQUIRED
+            .into_iter()
+            .filter(|x| !mapped.iter().any(|header| header == *x))
+            .collect()
+    }
+}
@@ -0,0 +1,259 @@
+use super::{
+    all_listings_report::AllListingsReport, archive, restock_inventory::RestockInventory,
+    AmzFbaInventory, Diagnostics, MonthlyStorageFees,
+};
+use crate::catalog::{Product, Source};
+use anyhow::{Context, Result};
+use csv::StringRecord;
+use serde::de::DeserializeOwned;
+use std::{
+    io::{BufRead, Read},
+    path::{Path, PathBuf},
+};
+
+/// The kinds of Amazon reports that Plaine knows how to read.
+///
+/// A report is identified by its header row, see [`ReportKind::identify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ReportKind {
+    MonthlyStorageFees,
+    AmzFbaInventory,
+    AllListingsReport,
+    RestockInventory,
+    Unknown,
+}
+
+impl ReportKind {
+    /// Every known kind, in the order they are tried by [`Self::identify`].
+    const KNOWN: [ReportKind; 4] = [
+        ReportKind::MonthlyStorageFees,
+        ReportKind::AmzFbaInventory,
+        ReportKind::AllListingsReport,
+        ReportKind::RestockInventory,
+    ];
+
+    /// Columns that must all be present for a header to be [`Self`].
+    ///
+    /// Columns are compared without regard to ascii case.
+    fn signature(&self) -> &'static [&'static str] {
+        match self {
+            ReportKind::MonthlyStorageFees => &["fnsku", "month_of_charge", "storage_rate"],
+            ReportKind::AmzFbaInventory => &[
+                "seller-sku",
+                "fulfillment-channel-sku",
+                "Quantity Available",
+            ],
+            ReportKind::AllListingsReport => {
+                &["seller-sku", "asin1", "item-name", "item-condition"]
+            }
+            ReportKind::RestockInventory => {
+                &["FNSKU", "Merchant SKU", "Recommended replenishment qty"]
+            }
+            ReportKind::Unknown => &[],
+        }
+    }
+
+    /// Identify the kind of report from its header row.
+    ///
+    /// Returns [`ReportKind::Unknown`] when no signature matches.
+    pub fn identify(header: &StringRecord) -> ReportKind {
+        let has = |col: &str| header.iter().any(|x| x.trim().eq_ignore_ascii_case(col));
+        Self::KNOWN
+            .into_iter()
+            .find(|kind| kind.signature().iter().all(|col| has(col)))
+            .unwrap_or(ReportKind::Unknown)
+    }
+}
+
+/// The result of inspecting the first line of a report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sniffed {
+    pub kind: ReportKind,
+    pub delimiter: u8,
+}
+
+/// Inspect the header row of `rdr`, guessing the delimiter and the kind.
+///
+/// Amazon reports are either tab or comma delimited, a tab anywhere in the
+/// header row is taken to mean the former.
+pub fn sniff<R: BufRead>(mut rdr: R) -> Result<Sniffed> {
+    let mut buf = vec![];
+    rdr.read_until(b'\n', &mut buf)?;
+    let line = String::from_utf8_lossy(&buf);
+    let line = line.trim_start_matches('\u{feff}');
+    let delimiter = if line.contains('\t') { b'\t' } else { b',' };
+
+    let header = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_reader(line.as_bytes())
+        .records()
+        .next()
+        .transpose()?
+        .unwrap_or_default();
+
+    let kind = ReportKind::identify(&header);
+    Ok(Sniffed { kind, delimiter })
+}
+
+/// A deserialized row of a report, along with the line it was read from.
+#[derive(Debug)]
+pub(super) struct Row<T> {
+    pub(super) line: u64,
+    pub(super) value: T,
+}
+
+/// Deserialize each row of `rdr` by its header, from the report at `file`.
+///
+/// Rows that fail are recorded within `diagnostics` and skipped.
+pub(super) fn rows<T, R>(
+    mut rdr: csv::Reader<R>,
+    file: &Path,
+    diagnostics: &mut Diagnostics,
+) -> Vec<Row<T>>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    let headers = rdr.headers().ok().cloned();
+    let mut rows = vec![];
+    for record in rdr.records() {
+        let deserialized = record.and_then(|x| {
+            let line = x.position().map(|p| p.line()).unwrap_or_default();
+            let value = x.deserialize(headers.as_ref())?;
+            Ok(Row { line, value })
+        });
+        match deserialized {
+            Ok(row) => rows.push(row),
+            Err(err) => diagnostics.reject_csv(file, headers.as_ref(), &err),
+        };
+    }
+    rows
+}
+
+/// A parsed report, holding the rows of whichever kind was sniffed.
+#[derive(Debug)]
+pub(super) enum ParsedReport {
+    MonthlyStorageFees(Vec<Row<MonthlyStorageFees>>),
+    AmzFbaInventory(Vec<Row<AmzFbaInventory>>),
+    AllListingsReport(Vec<Row<AllListingsReport>>),
+    RestockInventory(Vec<Row<RestockInventory>>),
+    Unknown,
+}
+
+impl ParsedReport {
+    pub(super) fn kind(&self) -> ReportKind {
+        match self {
+            ParsedReport::MonthlyStorageFees(_) => ReportKind::MonthlyStorageFees,
+            ParsedReport::AmzFbaInventory(_) => ReportKind::AmzFbaInventory,
+            ParsedReport::AllListingsReport(_) => ReportKind::AllListingsReport,
+            ParsedReport::RestockInventory(_) => ReportKind::RestockInventory,
+            ParsedReport::Unknown => ReportKind::Unknown,
+        }
+    }
+
+    /// Convert each row into the partial [`Product`] that it describes.
+    ///
+    /// Every value of the [`Product`] is tagged with the `file` and line
+    /// that it was read from.
+    pub(super) fn into_products(self, file: &Path) -> Vec<Product> {
+        let kind = self.kind();
+        let source = |line| Source::new(kind, file, line);
+        match self {
+            ParsedReport::MonthlyStorageFees(rows) => rows
+                .into_iter()
+                .map(|row| row.value.into_product(source(row.line)))
+                .collect(),
+            ParsedReport::AmzFbaInventory(rows) => rows
+                .into_iter()
+                .map(|row| row.value.into_product(source(row.line)))
+                .collect(),
+            ParsedReport::AllListingsReport(rows) => rows
+                .into_iter()
+                .map(|row| row.value.into_product(source(row.line)))
+                .collect(),
+            ParsedReport::RestockInventory(rows) => rows
+                .into_iter()
+                .map(|row| row.value.into_product(source(row.line)))
+                .collect(),
+            ParsedReport::Unknown => vec![],
+        }
+    }
+}
+
+/// Read each report at `path`, sniffing each header once to pick a parser.
+///
+/// Gzip files and the members of zip archives are read as well, each report
+/// is returned along with the name of the file it was read from, see
+/// [`archive::members`]. Files that are not recognized are returned as
+/// [`ParsedReport::Unknown`]. Rows that cannot be read are recorded within
+/// `diagnostics`.
+///
+/// # Errors
+///
+/// * The supplied path cannot be opened or read.
+/// * A header row is not valid csv.
+pub(super) fn read_reports<P>(
+    path: P,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<(PathBuf, ParsedReport)>>
+where
+    P: AsRef<Path>,
+{
+    let mut reports = vec![];
+    for member in archive::members(path)? {
+        let sniffed = sniff(member.bytes.as_slice())?;
+        let report = parse(member.bytes.as_slice(), &member.file, &sniffed, diagnostics)?;
+        reports.push((member.file, report));
+    }
+    Ok(reports)
+}
+
+/// Read each report at `path`, converting each row into a partial [`Product`].
+///
+/// See [`read_reports`].
+pub(crate) fn read_products<P>(
+    path: P,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<(ReportKind, Vec<Product>)>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let reports =
+        read_reports(path, diagnostics).with_context(|| format!("Reading {}", path.display()))?;
+    let products = reports
+        .into_iter()
+        .map(|(file, report)| (report.kind(), report.into_products(&file)))
+        .collect();
+    Ok(products)
+}
+
+/// Dispatch `rdr`, read from `file`, to the parser for the sniffed kind.
+fn parse<R: Read>(
+    rdr: R,
+    file: &Path,
+    sniffed: &Sniffed,
+    diagnostics: &mut Diagnostics,
+) -> Result<ParsedReport> {
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(sniffed.delimiter)
+        .flexible(true)
+        .from_reader(rdr);
+    let report = match sniffed.kind {
+        ReportKind::MonthlyStorageFees => ParsedReport::MonthlyStorageFees(
+            MonthlyStorageFees::from_reader(rdr, file, diagnostics),
+        ),
+        ReportKind::AmzFbaInventory => {
+            ParsedReport::AmzFbaInventory(AmzFbaInventory::from_reader(rdr, file, diagnostics))
+        }
+        ReportKind::AllListingsReport => {
+            ParsedReport::AllListingsReport(AllListingsReport::from_reader(rdr, file, diagnostics))
+        }
+        ReportKind::RestockInventory => {
+            ParsedReport::RestockInventory(RestockInventory::from_reader(rdr, file, diagnostics))
+        }
+        ReportKind::Unknown => ParsedReport::Unknown,
+    };
+    Ok(report)
+}
@@ -0,0 +1,155 @@
+use super::*;
+use crate::plan::condition::Condition;
+
+/// Representation of Amazon's Restock Inventory report.
+#[derive(Default, Debug, serde::Deserialize)]
+pub(super) struct RestockInventory {
+    #[serde(rename(deserialize = "Country"))]
+    pub(super) country: Option<String>,
+    #[serde(rename(deserialize = "Product Name"))]
+    pub(super) product_name: Option<String>,
+    #[serde(rename(deserialize = "FNSKU"))]
+    pub(super) fnsku: Option<String>,
+    #[serde(rename(deserialize = "Merchant SKU"))]
+    pub(super) msku: Option<String>,
+    #[serde(rename(deserialize = "ASIN"))]
+    pub(super) asin: Option<String>,
+    #[serde(rename(deserialize = "Condition"))]
+    pub(super) condition: Option<Condition>,
+    #[serde(rename(deserialize = "Recommended replenishment qty"))]
+    pub(super) recommended: Option<u32>,
+}
+
+impl RestockInventory {
+    /// Deserialize each row of an already sniffed report.
+    pub(super) fn from_reader<R: Read>(
+        rdr: csv::Reader<R>,
+        file: &Path,
+        diagnostics: &mut Diagnostics,
+    ) -> Vec<Row<RestockInventory>> {
+        rows(rdr, file, diagnostics)
+    }
+
+    /// Convert [`Self`] into a partial [`Product`], read from `source`.
+    pub(super) fn into_product(self, source: Source) -> Product {
+        let marketplace = self.marketplace();
+        Product {
+            fnsku: self.fnsku,
+            msku: source.tag(self.msku),
+            asin: source.tag(self.asin),
+            title: source.tag(self.product_name),
+            condition: source.tag(self.condition),
+            marketplace,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the marketplace of the country, when it is known.
+    fn marketplace(&self) -> Option<Marketplace> {
+        self.country.as_deref()?.parse().ok()
+    }
+
+    /// Convert [`Self`] into a loose [`Entry`] of the recommended units.
+    ///
+    /// Rows without a recommendation are skipped.
+    fn into_entry(self) -> Result<Option<Entry>> {
+        let units = self.recommended.unwrap_or_default();
+        if units == 0 {
+            return Ok(None);
+        };
+        let fnsku = self
+            .fnsku
+            .ok_or_else(|| ColumnError::new("FNSKU", "Expected an Fnsku"))?;
+        let mut entry = Entry::default();
+        entry.set_fnsku(fnsku);
+        entry.set_units(units as i32);
+        entry.set_id(gen_pw_uuid());
+        entry.set_loose(true);
+        Ok(Some(entry))
+    }
+}
+
+/// A draft plan of Amazon's recommended replenishment quantities.
+///
+/// Each recommendation becomes a single loose [`Entry`], to be packed into
+/// cases once the draft is opened.
+#[derive(Default, Debug)]
+pub struct RestockPlan {
+    file: PathBuf,
+    helper: Vec<Row<RestockInventory>>,
+}
+
+impl RestockPlan {
+    /// Read a draft plan from the Restock Inventory report at `path`.
+    ///
+    /// When every row is of the same country, that is the marketplace of the
+    /// draft, and entries are only enriched from report rows of it.
+    ///
+    /// # Errors
+    ///
+    /// * The supplied path is invalid.
+    /// * The file is not a Restock Inventory report.
+    /// * The reports used for enrichment cannot be read.
+    pub fn proc_from_path<P>(path: P) -> Result<Import>
+    where
+        P: AsRef<Path>,
+    {
+        let mut diagnostics = Diagnostics::default();
+        let plan = RestockPlan::from_path(path, &mut diagnostics)?;
+        let marketplace = plan.marketplace();
+        let mut entries = plan.into_entries(&mut diagnostics);
+        let enrichment = fill_entries(&mut entries, marketplace, &mut diagnostics)?;
+        Ok(Import {
+            entries,
+            marketplace,
+            enrichment,
+            diagnostics,
+        })
+    }
+
+    /// Read the rows of the report at `path`.
+    ///
+    /// A compressed `path` must hold exactly one Restock Inventory report.
+    pub(super) fn from_path<P>(path: P, diagnostics: &mut Diagnostics) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut found = vec![];
+        let mut plans = vec![];
+        for (file, report) in registry::read_reports(path, diagnostics)? {
+            match report {
+                ParsedReport::RestockInventory(helper) => plans.push(RestockPlan { file, helper }),
+                report => found.push(format!("{:?}", report.kind())),
+            };
+        }
+        match plans.len() {
+            1 => Ok(plans.remove(0)),
+            0 => bail!(
+                "{} is not a Restock Inventory report, found {}",
+                path.display(),
+                found.join(", ")
+            ),
+            n => bail!("{} holds {n} Restock Inventory reports", path.display()),
+        }
+    }
+
+    /// Returns the marketplace shared by every row of [`Self`].
+    pub(super) fn marketplace(&self) -> Option<Marketplace> {
+        let mut marketplaces = self.helper.iter().map(|x| x.value.marketplace());
+        let first = marketplaces.next()??;
+        marketplaces.all(|x| x == Some(first)).then_some(first)
+    }
+
+    /// Convert each recommendation of [`Self`] into an [`Entry`].
+    pub(super) fn into_entries(self, diagnostics: &mut Diagnostics) -> Vec<Entry> {
+        let mut entries = vec![];
+        for row in self.helper {
+            match row.value.into_entry() {
+                Ok(entry) => entries.extend(entry),
+                Err(err) => diagnostics.reject(&self.file, Some(row.line), &err),
+            };
+        }
+        entries
+    }
+}
@@ -1,6 +1,15 @@
-use crate::read::all_listings_report::Condition;
+use crate::{
+    catalog::Source,
+    plan::{condition::Condition, hazmat::Hazmat, marketplace::Marketplace},
+};
 
-use super::{all_listings_report::AllListingsReport, MonthlyStorageFees};
+use crate::plan::Plan;
+
+use super::{
+    all_listings_report::AllListingsReport, archive, is_workbook, read_products, sheet_names,
+    sniff, workbook, ColumnError, Diagnostics, GDrivePlan, MappingProfile, MonthlyStorageFees,
+    ReportKind, ReportMonth, RestockPlan,
+};
 use anyhow::anyhow;
 use csv::StringRecord;
 
@@ -105,6 +114,585 @@ fn all_listings_report() -> anyhow::Result<()> {
 
     assert_eq!(sku, "mon0000000003_udf");
     assert_eq!(asin, "B00000NYIC");
-    assert_eq!(condition, Condition::New);
+    assert_eq!(condition, Some(Condition::New));
+    Ok(())
+}
+
+#[test]
+fn sniff_report_kinds() -> anyhow::Result<()> {
+    let alr = "seller-sku\tasin1\titem-name\tproduct-id-type\titem-condition\tproduct-id\n";
+    let afi = "seller-sku,fulfillment-channel-sku,asin,condition-type,Warehouse-Condition-code,Quantity Available\n";
+    let msf = "\u{feff}asin,fnsku,product_name,weight,month_of_charge,storage_rate\n";
+    let plan = "FNSKU,Quantity,Case QT\n";
+
+    let sniffed = sniff(alr.as_bytes())?;
+    assert_eq!(sniffed.kind, ReportKind::AllListingsReport);
+    assert_eq!(sniffed.delimiter, b'\t');
+
+    let sniffed = sniff(afi.as_bytes())?;
+    assert_eq!(sniffed.kind, ReportKind::AmzFbaInventory);
+    assert_eq!(sniffed.delimiter, b',');
+
+    assert_eq!(sniff(msf.as_bytes())?.kind, ReportKind::MonthlyStorageFees);
+    assert_eq!(sniff(plan.as_bytes())?.kind, ReportKind::Unknown);
+    assert_eq!(sniff("".as_bytes())?.kind, ReportKind::Unknown);
+    Ok(())
+}
+
+fn storage_fee_columns() -> StringRecord {
+    StringRecord::from(vec![
+        "asin",
+        "fnsku",
+        "product_name",
+        "fulfillment_center",
+        "country_code",
+        "longest_side",
+        "median_side",
+        "shortest_side",
+        "measurement_units",
+        "weight",
+        "weight_units",
+        "item_volume",
+        "volume_units",
+        "product_size_tier",
+        "average_quantity_on_hand",
+        "average_quantity_pending_removal",
+        "estimated_total_item_volume",
+        "month_of_charge",
+        "storage_rate",
+        "currency",
+        "estimated_monthly_storage_fee",
+        "dangerous_goods_storage_type",
+        "eligible_for_inventory_discount",
+        "qualifies_for_inventory_discount",
+        "total_incentive_fee_amount",
+        "breakdown_incentive_fee_amount",
+        "average_quantity_customer_orders",
+    ])
+}
+
+/// A row of [`storage_fee_columns`], with `overrides` of column and value.
+fn storage_fee_row<'a>(overrides: &[(&str, &'a str)]) -> Vec<&'a str> {
+    let mut row = vec![
+        "B0B9CCP98J",
+        "X003C6LE0L",
+        "Sony XXXXXXX (Black)",
+        "ABE2",
+        "US",
+        "10.28",
+        "10.2",
+        "4.69",
+        "inches",
+        "2.94",
+        "pounds",
+        "0.2841",
+        "cubic feet",
+        "Standard-Size",
+        "0.57",
+        "0.0",
+        "0.161",
+        "2022-11",
+        "2.4",
+        "USD",
+        "0.39",
+        "--",
+        "N",
+        "N",
+        "0.0",
+        "--",
+        "0.0",
+    ];
+    let columns = storage_fee_columns();
+    for (column, value) in overrides {
+        let idx = columns.iter().position(|x| &x == column);
+        row[idx.expect("A storage fee column")] = value;
+    }
+    row
+}
+
+#[test]
+fn monthly_storage_fees_units() -> anyhow::Result<()> {
+    let metric = [
+        ("product_name", "Sony"),
+        ("fulfillment_center", "LEJ1"),
+        ("country_code", "DE"),
+        ("longest_side", "25.4"),
+        ("median_side", "12.7"),
+        ("shortest_side", "2.54"),
+        ("measurement_units", "centimeters"),
+        ("weight", "1.0"),
+        ("weight_units", "kilograms"),
+        ("item_volume", "0.0283168"),
+        ("volume_units", "cubic meters"),
+        ("currency", "EUR"),
+    ];
+    let row = storage_fee_row(&metric);
+    let hdr = storage_fee_columns();
+    let de: MonthlyStorageFees = StringRecord::from(row).deserialize(Some(&hdr))?;
+    let de = de.normalize()?;
+
+    let close = |a: Option<f32>, b: f32| (a.unwrap_or_default() - b).abs() < 0.01;
+    assert!(close(de.longest_side, 10.0));
+    assert!(close(de.shortest_side, 1.0));
+    assert!(close(de.weight, 2.2046));
+    assert!(close(de.item_volume, 1.0));
+    // 2.4 per cubic meter.
+    assert!(close(de.storage_rate, 0.068));
+
+    // Empty units are in the units of the marketplace.
+    let blank_units = [
+        ("measurement_units", ""),
+        ("weight_units", ""),
+        ("volume_units", ""),
+    ];
+    let blank = storage_fee_row(&[&metric[..], &blank_units].concat());
+    let de: MonthlyStorageFees = StringRecord::from(blank).deserialize(Some(&hdr))?;
+    let de = de.normalize()?;
+    assert!(close(de.longest_side, 10.0));
+    assert!(close(de.weight, 2.2046));
+    assert!(close(de.item_volume, 1.0));
+
+    // Country codes that are not a known marketplace are kept for any.
+    let zz = [&metric[..], &blank_units, &[("country_code", "ZZ")]].concat();
+    let header = hdr.iter().collect::<Vec<_>>().join(",");
+    let csv = format!("{header}\n{}\n", storage_fee_row(&zz).join(","));
+    let mut diagnostics = Diagnostics::default();
+    let rdr = csv::Reader::from_reader(csv.as_bytes());
+    let zz = MonthlyStorageFees::from_reader(rdr, "fees.csv".as_ref(), &mut diagnostics);
+    assert!(diagnostics.is_empty());
+    let warnings = diagnostics.warnings().collect::<Vec<_>>();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].column.as_deref(), Some("country_code"));
+    let mut csv = vec![];
+    diagnostics.write_csv(&mut csv)?;
+    let csv = String::from_utf8(csv)?;
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("kind,file,line,column,reason"));
+    assert!(lines
+        .next()
+        .is_some_and(|x| x.starts_with("warning,fees.csv,2,country_code,")));
+    assert_eq!(lines.next(), None);
+    let source = Source::new(ReportKind::MonthlyStorageFees, "fees.csv", 2);
+    let product = zz[0].value.clone().into_product(source);
+    assert_eq!(product.marketplace, None);
+    assert!(product.get_fulfillment_centers().contains("LEJ1"));
+    assert!(close(product.get_unit_storage_fee(), 0.684));
+
+    let row = storage_fee_row(&[&metric[..], &[("measurement_units", "furlongs")]].concat());
+    let de: MonthlyStorageFees = StringRecord::from(row).deserialize(Some(&hdr))?;
+    let err = de.normalize().expect_err("furlongs are not a unit");
+    assert!(err.to_string().contains("furlongs"));
+    Ok(())
+}
+
+#[test]
+fn report_month_parse() -> anyhow::Result<()> {
+    let month: ReportMonth = "2022-11".parse()?;
+    assert_eq!((month.year(), month.month()), (2022, 11));
+    assert_eq!("2022-11-30".parse::<ReportMonth>()?, month);
+    assert_eq!("2022/11".parse::<ReportMonth>()?.to_string(), "2022-11");
+    assert!("2022-12".parse::<ReportMonth>()? > month);
+    assert!("2022-13".parse::<ReportMonth>().is_err());
+    assert!("Nov-22".parse::<ReportMonth>().is_err());
+
+    let mut row = storage_fee_columns().iter().map(|_| "").collect::<Vec<_>>();
+    row[1] = "X003C6LE0L";
+    row[17] = "November";
+    let hdr = storage_fee_columns();
+    let msf: MonthlyStorageFees = StringRecord::from(row).deserialize(Some(&hdr))?;
+    let err = msf.normalize().expect_err("November has no year");
+    let column = err.downcast_ref::<ColumnError>().map(|x| x.column.as_str());
+    assert_eq!(column, Some("month_of_charge"));
+    Ok(())
+}
+
+#[test]
+fn monthly_storage_fees_hazmat() -> anyhow::Result<()> {
+    let row = |hazmat| {
+        storage_fee_row(&[
+            ("product_name", "Spray"),
+            ("fulfillment_center", "LEJ1"),
+            ("item_volume", "0.02"),
+            ("dangerous_goods_storage_type", hazmat),
+        ])
+    };
+    let hdr = storage_fee_columns();
+    let source = Source::new(ReportKind::MonthlyStorageFees, "msf.csv", 2);
+    let hazmat = |row: Vec<&str>| -> anyhow::Result<Option<Hazmat>> {
+        let msf: MonthlyStorageFees = StringRecord::from(row).deserialize(Some(&hdr))?;
+        let product = msf.normalize()?.into_product(source.clone());
+        Ok(product.hazmat.map(|x| x.value))
+    };
+    assert_eq!(hazmat(row("Aerosols"))?, Some(Hazmat::Aerosol));
+    assert_eq!(hazmat(row("--"))?, Some(Hazmat::NonHazmat));
+    assert_eq!(hazmat(row("Lithium Batteries"))?, Some(Hazmat::Other));
+    Ok(())
+}
+
+#[test]
+fn gdrive_plan_diagnostics() -> anyhow::Result<()> {
+    let csv = "FNSKU,Quantity,Pack Type,Case QT\n\
+               X001,24,Case,12\n\
+               X002,25,Case,12\n\
+               ,10,Case,5\n\
+               X003,ten,Case,5\n\
+               X004,7,Loose,\n";
+    let rdr = csv::Reader::from_reader(csv.as_bytes());
+
+    let mut diagnostics = Diagnostics::default();
+    let plan = GDrivePlan::from_reader(
+        rdr,
+        "plan.csv".into(),
+        &MappingProfile::default(),
+        &mut diagnostics,
+    );
+    let entries = plan.into_entries(&mut diagnostics);
+
+    assert_eq!(entries.len(), 3);
+    let loose = entries.iter().filter(|x| x.is_loose()).collect::<Vec<_>>();
+    assert_eq!((loose.len(), loose[0].get_fnsku()), (1, "X004"));
+    let rejected = diagnostics.iter().collect::<Vec<_>>();
+    assert_eq!(rejected.len(), 3);
+
+    let lines = rejected.iter().map(|x| x.line).collect::<Vec<_>>();
+    assert!(lines.contains(&Some(3)));
+    assert!(lines.contains(&Some(4)));
+    assert!(lines.contains(&Some(5)));
+    assert!(rejected
+        .iter()
+        .all(|x| matches!(x.column.as_deref(), Some("Quantity" | "FNSKU"))));
+    Ok(())
+}
+
+#[test]
+fn gdrive_plan_mixed_boxes() -> anyhow::Result<()> {
+    let csv =
+        "FNSKU,Quantity,Pack Type,Case QT,Box ID,Case Length,Case Width,Case Height,Case Weight\n\
+               X001,24,Case,12,,10,12,8,20\n\
+               X002,6,Case,3,BOX-1,18,14,10,31\n\
+               X003,4,Case,,BOX-1,,,,\n\
+               X004,5,Case,,BOX-1,18,14,10,40\n";
+    let rdr = csv::Reader::from_reader(csv.as_bytes());
+
+    let mut diagnostics = Diagnostics::default();
+    let plan = GDrivePlan::from_reader(
+        rdr,
+        "plan.csv".into(),
+        &MappingProfile::default(),
+        &mut diagnostics,
+    );
+    let entries = plan.into_entries(&mut diagnostics);
+    let reimported = GDrivePlan::from_reader(
+        csv::Reader::from_reader(csv.as_bytes()),
+        "plan.csv".into(),
+        &MappingProfile::default(),
+        &mut Diagnostics::default(),
+    )
+    .into_entries(&mut Diagnostics::default());
+
+    // The conflicting weight of X004 is rejected.
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(entries.single_fnsku_cases().len(), 2);
+
+    let mixed = entries.multi_fnsku_cases();
+    assert_eq!(mixed.len(), 2);
+    let id = mixed[0].get_id();
+    assert!(id.starts_with("BOX-1-"));
+    assert!(mixed.iter().all(|x| x.get_id() == id));
+    // Box ids of another import of the same plan do not collide.
+    assert!(reimported.iter().all(|x| x.get_id() != id));
+    assert!(mixed
+        .iter()
+        .all(|x| x.get_case_dimensions() == Some([18.0, 14.0, 10.0])));
+    assert!(mixed.iter().all(|x| x.get_total_pounds() == &Some(31.0)));
+    assert_eq!(entries.get_case_named(id).units(), 10);
+    Ok(())
+}
+
+#[test]
+fn workbook_extensions() {
+    assert!(is_workbook("plan.xlsx"));
+    assert!(is_workbook("plan.ODS"));
+    assert!(!is_workbook("plan.csv"));
+    assert!(!is_workbook("plan"));
+}
+
+/// A workbook with a single "Plan" sheet, of a header and one row.
+fn plan_workbook() -> anyhow::Result<Vec<u8>> {
+    use std::io::Write;
+
+    // The fewest parts of an xlsx that still open, with inline text cells.
+    let parts = [
+        (
+            "[Content_Types].xml",
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+<Default Extension="xml" ContentType="application/xml"/>
+<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>
+<Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>
+</Types>"#,
+        ),
+        (
+            "_rels/.rels",
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>
+</Relationships>"#,
+        ),
+        (
+            "xl/workbook.xml",
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+<sheets><sheet name="Plan" sheetId="1" r:id="rId1"/></sheets>
+</workbook>"#,
+        ),
+        (
+            "xl/_rels/workbook.xml.rels",
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/>
+</Relationships>"#,
+        ),
+        (
+            "xl/worksheets/sheet1.xml",
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>
+<row r="1">
+<c r="A1" t="inlineStr"><is><t>Sku Code</t></is></c>
+<c r="B1" t="inlineStr"><is><t>Quantity</t></is></c>
+<c r="C1" t="inlineStr"><is><t>Case QT</t></is></c>
+<c r="D1" t="inlineStr"><is><t>Unit Weight</t></is></c>
+</row>
+<row r="2">
+<c r="A2" t="inlineStr"><is><t>00123</t></is></c>
+<c r="B2"><v>24</v></c>
+<c r="C2"><v>12</v></c>
+<c r="D2"><v>2.5</v></c>
+</row>
+</sheetData></worksheet>"#,
+        ),
+    ];
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, xml) in parts {
+        zip.start_file(name, options)?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[test]
+fn workbook_sheet_as_csv() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("{}.xlsx", uuid::Uuid::new_v4()));
+    std::fs::write(&path, plan_workbook()?)?;
+    let names = sheet_names(&path);
+    let bytes = workbook::sheet_as_csv(&path, "Plan");
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(names?, ["Plan"]);
+    // Integral numbers lose their ".0", while text keeps its leading zeros.
+    let bytes = bytes?;
+    let csv = String::from_utf8(bytes.clone())?;
+    assert_eq!(
+        csv,
+        "Sku Code,Quantity,Case QT,Unit Weight\n00123,24,12,2.5\n"
+    );
+
+    let profile = MappingProfile {
+        name: String::from("Sheet"),
+        columns: [(String::from("Sku Code"), String::from("FNSKU"))].into(),
+        ..Default::default()
+    };
+    let rdr = csv::Reader::from_reader(bytes.as_slice());
+    let mut diagnostics = Diagnostics::default();
+    let plan = GDrivePlan::from_reader(rdr, "plan.xlsx[Plan]".into(), &profile, &mut diagnostics);
+    let entries = plan.into_entries(&mut diagnostics);
+    assert!(diagnostics.is_empty());
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|x| x.get_fnsku() == "00123"));
+    assert_eq!(entries.units(), 24);
+    Ok(())
+}
+
+#[test]
+fn gdrive_plan_mapping_profile() -> anyhow::Result<()> {
+    let csv = "Sku Code,Units,Packing,Per Box\n\
+               X001,24,Case,12\n\
+               X002,7,Bulk,\n";
+    let profile = MappingProfile {
+        name: String::from("Renamed"),
+        columns: [
+            ("Sku Code", "FNSKU"),
+            ("Units", "Quantity"),
+            ("Packing", "Pack Type"),
+        ]
+        .into_iter()
+        .chain([("Per Box", "Case QT")])
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .collect(),
+        loose: vec![String::from("bulk")],
+    };
+    profile.validate()?;
+
+    let rdr = csv::Reader::from_reader(csv.as_bytes());
+    let mut diagnostics = Diagnostics::default();
+    let plan = GDrivePlan::from_reader(rdr, "plan.csv".into(), &profile, &mut diagnostics);
+    let entries = plan.into_entries(&mut diagnostics);
+    assert!(diagnostics.is_empty());
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries.units(), 31);
+
+    // The default profile does not know these columns.
+    let rdr = csv::Reader::from_reader(csv.as_bytes());
+    let mut diagnostics = Diagnostics::default();
+    let default = MappingProfile::default();
+    let plan = GDrivePlan::from_reader(rdr, "plan.csv".into(), &default, &mut diagnostics);
+    assert!(plan.into_entries(&mut diagnostics).is_empty());
+    let missing = diagnostics.iter().filter_map(|x| x.column.as_deref());
+    assert_eq!(missing.collect::<Vec<_>>(), ["FNSKU", "Quantity"]);
+    Ok(())
+}
+
+#[test]
+fn mapping_profiles_skip_invalid() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    let profile = MappingProfile {
+        name: String::from("Warehouse"),
+        columns: [(String::from("Sku Code"), String::from("FNSKU"))].into(),
+        ..Default::default()
+    };
+    let saved = profile.save(&dir);
+    std::fs::write(dir.join("truncated.json"), "{\"name\": ")?;
+    let unknown = r#"{"name": "Unknown", "columns": {"Sku": "Sku"}}"#;
+    std::fs::write(dir.join("unknown.json"), unknown)?;
+    let mut diagnostics = Diagnostics::default();
+    let profiles = MappingProfile::load_all(&dir, &mut diagnostics);
+    std::fs::remove_dir_all(&dir)?;
+
+    saved?;
+    assert_eq!(profiles, [profile]);
+    assert_eq!(diagnostics.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn restock_inventory_draft() -> anyhow::Result<()> {
+    let report = "Country\tProduct Name\tFNSKU\tMerchant SKU\tASIN\tCondition\tAvailable\tRecommended replenishment qty\n\
+                  US\tWidget\tX001\tW-1\tB001\tNew\t4\t36\n\
+                  US\tGadget\tX002\tG-1\tB002\tNew\t90\t0\n\
+                  US\tGizmo\t\tZ-1\tB003\tNew\t0\t12\n";
+    let path = std::env::temp_dir().join(format!("{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, report)?;
+
+    let sniffed = sniff(report.as_bytes())?;
+    assert_eq!(sniffed.kind, ReportKind::RestockInventory);
+
+    let mut diagnostics = Diagnostics::default();
+    let plan = RestockPlan::from_path(&path, &mut diagnostics);
+    std::fs::remove_file(&path)?;
+    let plan = plan?;
+    assert_eq!(plan.marketplace(), Some(Marketplace::US));
+    let entries = plan.into_entries(&mut diagnostics);
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].get_fnsku(), "X001");
+    assert_eq!(entries[0].get_units(), 36);
+    assert_eq!(diagnostics.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn compressed_reports() -> anyhow::Result<()> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let afi = "seller-sku,fulfillment-channel-sku,asin,condition-type,Warehouse-Condition-code,Quantity Available\n\
+               sku-a,X00A,B00A,NewItem,SELLABLE,7\n";
+    let alr = "seller-sku\tasin1\titem-name\tproduct-id-type\titem-condition\tproduct-id\n\
+               sku-a\tB00A\tWidget\t1\t11\tB00A\n";
+    let gzip = |text: &str| -> anyhow::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(text.as_bytes())?;
+        Ok(encoder.finish()?)
+    };
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir)?;
+    let gz = dir.join("inventory.txt.gz");
+    std::fs::write(&gz, gzip(afi)?)?;
+
+    let zipped = dir.join("reports.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zipped)?);
+    let options = zip::write::SimpleFileOptions::default();
+    zip.add_directory("reports/", options)?;
+    zip.start_file("reports/listings.txt", options)?;
+    zip.write_all(alr.as_bytes())?;
+    zip.start_file("reports/inventory.txt.gz", options)?;
+    zip.write_all(&gzip(afi)?)?;
+    zip.finish()?;
+
+    let mut diagnostics = Diagnostics::default();
+    let from_gz = read_products(&gz, &mut diagnostics);
+    let from_zip = read_products(&zipped, &mut diagnostics);
+    std::fs::remove_dir_all(&dir)?;
+
+    let from_gz = from_gz?;
+    assert_eq!(from_gz.len(), 1);
+    assert_eq!(from_gz[0].0, ReportKind::AmzFbaInventory);
+    assert_eq!(from_gz[0].1[0].get_available(), Some(7));
+
+    let kinds = from_zip?
+        .into_iter()
+        .map(|(kind, _)| kind)
+        .collect::<Vec<_>>();
+    let expected = [ReportKind::AllListingsReport, ReportKind::AmzFbaInventory];
+    assert_eq!(kinds, expected);
+    assert!(diagnostics.is_empty());
+    Ok(())
+}
+
+#[test]
+fn zip_members_skip_junk() -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let afi = "seller-sku,fulfillment-channel-sku,asin,condition-type,Warehouse-Condition-code,Quantity Available\n\
+               sku-a,X00A,B00A,NewItem,SELLABLE,7\n";
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir)?;
+    let zipped = dir.join("plans.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zipped)?);
+    let options = zip::write::SimpleFileOptions::default();
+    let members: [(&str, &[u8]); 6] = [
+        ("inventory.txt", afi.as_bytes()),
+        ("__MACOSX/._inventory.txt", b"\x00\x05\x16\x07"),
+        (".DS_Store", b"\x00\x00\x00\x01Bud1"),
+        ("README.txt", b"Exported plans"),
+        ("label.pdf", b"%PDF-1.4"),
+        ("plan.xlsx", &plan_workbook()?),
+    ];
+    for (name, bytes) in members {
+        zip.start_file(name, options)?;
+        zip.write_all(bytes)?;
+    }
+    zip.finish()?;
+
+    let members = archive::members(&zipped);
+    std::fs::remove_dir_all(&dir)?;
+
+    let members = members?;
+    let names = members
+        .iter()
+        .map(|x| x.file.file_name().map(|x| x.to_string_lossy().to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            Some(String::from("plans.zip[inventory.txt]")),
+            Some(String::from("plans.zip[plan.xlsx]")),
+        ]
+    );
+    // Workbooks are read as the csv of their first sheet.
+    let csv = String::from_utf8(members[1].bytes.clone())?;
+    assert!(csv.starts_with("Sku Code,Quantity"));
     Ok(())
 }
@@ -0,0 +1,131 @@
+use anyhow::{bail, Error};
+use std::str::FromStr;
+
+/// Units of length found in Amazon reports.
+///
+/// Inches are the canonical unit, see [`LengthUnit::to_inches`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthUnit {
+    #[default]
+    Inches,
+    Centimeters,
+    Millimeters,
+}
+
+impl LengthUnit {
+    pub fn to_inches(&self, value: f32) -> f32 {
+        match self {
+            LengthUnit::Inches => value,
+            LengthUnit::Centimeters => value / 2.54,
+            LengthUnit::Millimeters => value / 25.4,
+        }
+    }
+}
+
+impl FromStr for LengthUnit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unit = match s.trim().to_ascii_lowercase().as_str() {
+            "inches" | "inch" | "in" => LengthUnit::Inches,
+            "centimeters" | "centimetres" | "centimeter" | "centimetre" | "cm" => {
+                LengthUnit::Centimeters
+            }
+            "millimeters" | "millimetres" | "millimeter" | "millimetre" | "mm" => {
+                LengthUnit::Millimeters
+            }
+            _ => bail!("Unknown measurement unit '{s}'"),
+        };
+        Ok(unit)
+    }
+}
+
+/// Units of weight found in Amazon reports.
+///
+/// Pounds are the canonical unit, see [`WeightUnit::to_pounds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WeightUnit {
+    #[default]
+    Pounds,
+    Ounces,
+    Kilograms,
+    Grams,
+}
+
+impl WeightUnit {
+    pub fn to_pounds(&self, value: f32) -> f32 {
+        match self {
+            WeightUnit::Pounds => value,
+            WeightUnit::Ounces => value / 16.0,
+            WeightUnit::Kilograms => value * 2.204_622_6,
+            WeightUnit::Grams => value * 0.002_204_622_6,
+        }
+    }
+}
+
+impl FromStr for WeightUnit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unit = match s.trim().to_ascii_lowercase().as_str() {
+            "pounds" | "pound" | "lbs" | "lb" => WeightUnit::Pounds,
+            "ounces" | "ounce" | "oz" => WeightUnit::Ounces,
+            "kilograms" | "kilogram" | "kg" => WeightUnit::Kilograms,
+            "grams" | "gram" | "g" => WeightUnit::Grams,
+            _ => bail!("Unknown weight unit '{s}'"),
+        };
+        Ok(unit)
+    }
+}
+
+/// Units of volume found in Amazon reports.
+///
+/// Cubic feet are the canonical unit, see [`VolumeUnit::to_cubic_feet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VolumeUnit {
+    #[default]
+    CubicFeet,
+    CubicInches,
+    CubicMeters,
+    CubicCentimeters,
+}
+
+impl VolumeUnit {
+    pub fn to_cubic_feet(&self, value: f32) -> f32 {
+        match self {
+            VolumeUnit::CubicFeet => value,
+            VolumeUnit::CubicInches => value / 1728.0,
+            VolumeUnit::CubicMeters => value * 35.314_667,
+            VolumeUnit::CubicCentimeters => value * 0.000_035_314_667,
+        }
+    }
+}
+
+impl FromStr for VolumeUnit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unit = match s.trim().to_ascii_lowercase().as_str() {
+            "cubic feet" | "cubic foot" | "ft3" => VolumeUnit::CubicFeet,
+            "cubic inches" | "cubic inch" | "in3" => VolumeUnit::CubicInches,
+            "cubic meters" | "cubic metres" | "cubic meter" | "cubic metre" | "m3" => {
+                VolumeUnit::CubicMeters
+            }
+            "cubic centimeters" | "cubic centimetres" | "cubic centimeter" | "cubic centimetre"
+            | "cm3" => VolumeUnit::CubicCentimeters,
+            _ => bail!("Unknown volume unit '{s}'"),
+        };
+        Ok(unit)
+    }
+}
+
+/// Parse an optional unit column, empty columns are the `default` unit.
+pub(super) fn parse_unit<U>(s: &Option<String>, default: U) -> Result<U, Error>
+where
+    U: FromStr<Err = Error>,
+{
+    match s.as_deref().map(str::trim) {
+        None | Some("") => Ok(default),
+        Some(unit) => unit.parse(),
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use calamine::{open_workbook_auto, open_workbook_auto_from_rs, Data, Reader, Sheets};
+use std::{
+    io::{Cursor, Read, Seek},
+    path::Path,
+};
+
+/// File extensions that are read as spreadsheet workbooks rather than csv.
+const EXTENSIONS: [&str; 5] = ["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+/// Returns true if the file at `path` is a spreadsheet workbook.
+///
+/// This only looks at the file extension.
+pub fn is_workbook<P>(path: P) -> bool
+where
+    P: AsRef<Path>,
+{
+    let Some(ext) = path.as_ref().extension().and_then(|x| x.to_str()) else {
+        return false;
+    };
+    EXTENSIONS.iter().any(|x| x.eq_ignore_ascii_case(ext))
+}
+
+/// Returns the name of each sheet in the workbook at `path`, in order.
+///
+/// # Errors
+///
+/// The file cannot be opened as a workbook.
+pub fn sheet_names<P>(path: P) -> Result<Vec<String>>
+where
+    P: AsRef<Path>,
+{
+    let workbook = open_workbook_auto(path)?;
+    Ok(workbook.sheet_names())
+}
+
+/// Write the cells of `sheet` as csv, so it can be read like a csv plan.
+///
+/// Each row of the sheet is a line of the csv, starting with the header.
+/// Text cells are kept as they are, which preserves leading zeros.
+///
+/// # Errors
+///
+/// * The file cannot be opened as a workbook.
+/// * The workbook does not have a sheet named `sheet`.
+pub(super) fn sheet_as_csv<P>(path: P, sheet: &str) -> Result<Vec<u8>>
+where
+    P: AsRef<Path>,
+{
+    range_as_csv(open_workbook_auto(path)?, sheet)
+}
+
+/// Write the cells of the first sheet of the workbook held in `bytes` as
+/// csv, see [`sheet_as_csv`].
+///
+/// # Errors
+///
+/// * `bytes` cannot be opened as a workbook.
+/// * The workbook has no sheets.
+pub(super) fn first_sheet_as_csv(bytes: &[u8]) -> Result<Vec<u8>> {
+    let workbook = open_workbook_auto_from_rs(Cursor::new(bytes))?;
+    let first = workbook
+        .sheet_names()
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("The workbook has no sheets"))?;
+    range_as_csv(workbook, &first)
+}
+
+fn range_as_csv<RS: Read + Seek>(mut workbook: Sheets<RS>, sheet: &str) -> Result<Vec<u8>> {
+    let range = workbook
+        .worksheet_range(sheet)
+        .map_err(|err| anyhow!("Sheet '{sheet}' could not be read: {err}"))?;
+
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(vec![]);
+    for row in range.rows() {
+        wtr.write_record(row.iter().map(cell_to_string))?;
+    }
+    Ok(wtr.into_inner()?)
+}
+
+/// Integral numbers are written without a trailing ".0".
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Float(x) if x.fract() == 0.0 && x.abs() < 1e15 => format!("{}", *x as i64),
+        _ => cell.to_string(),
+    }
+}
@@ -1,17 +1,33 @@
 use crate::{
-    plan::{Entry, Plan},
+    plan::{status::CheckRow, storage::StorageEstimate, Entry, Plan},
+    read::Diagnostics,
     Brn,
 };
 use anyhow::{anyhow, Context, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub fn write_check_file(entry_vec: Vec<Entry>, plan_name: String) -> std::io::Result<()> {
+/// Write the check file of a plan, with the monthly storage cost of each
+/// Fnsku when there is an `estimate`.
+///
+/// The notes of each Fnsku carry the warnings of [`CheckRow::notes`].
+pub fn write_check_file(
+    entry_vec: Vec<Entry>,
+    plan_name: String,
+    estimate: Option<&StorageEstimate>,
+) -> std::io::Result<()> {
     let mut contents = String::new();
     let name = format!("{plan_name}\n");
 
-    let hdr = "ASIN,TITLE,UNITS,SIZE,FNSKU,UPC,COUNT,NOTES\n".to_string();
+    let mut hdr = "ASIN,TITLE,UNITS,SIZE,FNSKU,UPC,COUNT,NOTES".to_string();
+    if let Some(estimate) = estimate {
+        let currency = &estimate.currency;
+        hdr.push_str(&format!(",OFF_PEAK_{currency},PEAK_{currency}"));
+    };
+    hdr.push('\n');
     contents.push_str(&name);
     contents.push_str(&hdr);
+    let money = |x: Option<f32>| x.map(|x| format!("{x:.2}")).unwrap_or_default();
+    let rows = CheckRow::by_fnsku(&entry_vec);
     for entry in entry_vec.get_as_sums() {
         // Is the fnsku in the selected set?
 
@@ -20,7 +36,16 @@ pub fn write_check_file(entry_vec: Vec<Entry>, plan_name: String) -> std::io::Re
         let asin = entry.get_asin().clone().unwrap_or_default();
         let title = entry.get_title().clone().unwrap_or_default();
         let units = entry.get_units().to_string();
-        let row = format!("\"{asin}\",\"{title}\",\"{units}\",\"{amz_size}\",\"{fnsku}\",'\n");
+        let notes = rows.get(fnsku).map(|x| x.notes()).unwrap_or_default();
+        let mut row =
+            format!("\"{asin}\",\"{title}\",\"{units}\",\"{amz_size}\",\"{fnsku}\",',,\"{notes}\"");
+        if let Some(estimate) = estimate {
+            let cost = estimate.costs.iter().find(|x| x.fnsku == fnsku);
+            let off_peak = money(cost.and_then(|x| x.off_peak));
+            let peak = money(cost.and_then(|x| x.peak));
+            row.push_str(&format!(",\"{off_peak}\",\"{peak}\""));
+        };
+        row.push('\n');
         contents.push_str(&row);
     }
     let path = PathBuf::from(format!("{plan_name}-CheckFile.csv"));
@@ -51,3 +76,19 @@ pub fn write_upload_txt(entry_vec: Vec<Entry>, brn: Brn) -> Result<()> {
     let path = PathBuf::from(format!("{brn}-Upload.txt"));
     std::fs::write(path, header).context("fs::write failed")
 }
+
+/// Write the rejected rows of an import to a csv file at `path`.
+pub fn write_diagnostics_csv<P>(diagnostics: &Diagnostics, path: P) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let file = std::fs::File::create(path)?;
+    diagnostics.write_csv(file)
+}
+
+/// Write the projected storage cost of a branch to `{brn}-StorageEstimate.csv`.
+pub fn write_storage_estimate_csv(estimate: &StorageEstimate, brn: Brn) -> Result<()> {
+    let path = PathBuf::from(format!("{brn}-StorageEstimate.csv"));
+    let file = std::fs::File::create(path)?;
+    estimate.write_csv(file)
+}